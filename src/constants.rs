@@ -1,4 +1,15 @@
 pub const CAM_ZOOM_STEP_DEFAULT: f32 = 1.0;
 pub const CAM_ZOOM_MIN_DEFAULT: f32 = 1.0;
 pub const CAM_ZOOM_MAX_DEFAULT: f32 = 20.0;
-pub const DITHER_RES_DIVISOR_DEFAULT: i32 = 5;
\ No newline at end of file
+pub const DITHER_RES_DIVISOR_DEFAULT: i32 = 5;
+pub const HEIGHT_HIT_BONUS_PER_LEVEL: i32 = 5;
+pub const HEIGHT_DAMAGE_BONUS_PER_LEVEL: i32 = 1;
+pub const CRIT_DAMAGE_MULTIPLIER: i32 = 2;
+pub const PARTIAL_COVER_HIT_PENALTY: i32 = 20;
+pub const LOS_EYE_HEIGHT: f32 = 0.75; // Up from the bottom of a unit's top cell
+pub const LOS_TARGET_LOW: f32 = 0.25;
+pub const LOS_TARGET_HIGH: f32 = 0.75;
+pub const SAVE_VERSION: i64 = 2; // Bump whenever saved data changes meaning, older saves are refused
+pub const SAVE_DIR: &str = "user://saves";
+pub const REPLAY_VERSION: i64 = 1; // Replays from other versions are refused rather than desyncing
+pub const REPLAY_DIR: &str = "user://replays";
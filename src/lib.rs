@@ -2,6 +2,7 @@ use godot::init::{gdextension, ExtensionLibrary};
 
 mod constants;
 mod nodes;
+mod resources;
 mod types;
 
 struct Game;
@@ -0,0 +1,127 @@
+use crate::nodes::{FieldCharacter, FieldGripMap, TurnManager};
+use crate::types::{plan_turn, AiAction, AiOrders, AiPlan, BattleState, CharType, UnitRanges, UnitState};
+
+use std::{collections::{HashMap, VecDeque}, rc::Rc};
+use godot::{builtin::Vector3i, classes::{INode, Node}, meta::ToGodot, obj::{Base, Gd, GdRef, WithBaseField}, prelude::{godot_api, GodotClass}};
+
+// Takes the turns of every unit the player doesn't control, one unit at a time
+#[derive(GodotClass)]
+#[class(base=Node)]
+pub struct BattleAi {
+    base: Base<Node>,
+    unit_queue: VecDeque<Gd<FieldCharacter>>,
+    pending_action: Option<(Gd<FieldCharacter>, Option<AiAction>)>, // Unit walking to its dest, and what it does after
+    disabled: bool, // Units are left for something else to move, like a replay
+
+    #[export] pub field: Option<Gd<FieldGripMap>>,
+    #[export] pub turn_manager: Option<Gd<TurnManager>>,
+    #[export] pub has_objective: bool,
+    #[export] pub objective: Vector3i, // Headed for when nothing can be attacked, if has_objective
+}
+
+#[godot_api]
+impl INode for BattleAi {
+    fn init(base: Base<Node>) -> Self {
+        Self {
+            base,
+            unit_queue: VecDeque::new(),
+            pending_action: None,
+            disabled: false,
+
+            field: None,
+            turn_manager: None,
+            has_objective: false,
+            objective: Vector3i::ZERO,
+        }
+    }
+
+    fn process(&mut self, _: f64) {
+        let Some(mut field) = self.field.clone() else { return; };
+
+        // Nothing left to fight for
+        if self.turn_manager.as_ref().is_some_and(|turn_manager| turn_manager.bind().is_battle_over()) {
+            self.unit_queue.clear();
+            self.pending_action = None;
+            return;
+        }
+
+        // Wait for the last unit to get where it was going
+        if field.bind().is_any_char_moving() { return; }
+
+        if let Some((unit, action)) = self.pending_action.take() {
+            // Could have died to a counter or fall damage on the way
+            if !unit.is_instance_valid() { return; }
+
+            let acted: bool = match action {
+                Some(AiAction::Attack(target)) => field.bind_mut().attack_char(unit.clone(), target),
+                Some(AiAction::Heal(target)) => field.bind_mut().heal_char(unit.clone(), target),
+                None => false,
+            };
+            if !acted && unit.is_instance_valid() {
+                field.bind_mut().wait_char(unit);
+            }
+            return;
+        }
+
+        while let Some(unit) = self.unit_queue.pop_front() {
+            if !unit.is_instance_valid() { continue; }
+
+            // Could have died since the phase began
+            let Some(plan) = self.plan_unit_turn(&field.bind(), &unit) else { continue; };
+
+            if plan.dest != unit.bind().field_position {
+                field.bind_mut().move_char(unit.clone(), plan.dest);
+            }
+
+            self.pending_action = Some((unit, plan.action));
+            break;
+        }
+    }
+}
+
+#[godot_api]
+impl BattleAi {
+    // Queue up every unit of the phase, called by the turn manager
+    #[func]
+    pub fn start_phase(&mut self, phase: CharType) {
+        if self.disabled { return; }
+        let Some(field) = &self.field else { return; };
+        let field: GdRef<'_, FieldGripMap> = field.bind();
+
+        // Some may already be done if the phase was picked up from a save
+        self.unit_queue = field.get_chars_of_type(phase).into_iter()
+            .filter(|unit| !field.get_battle_state().turn.is_spent(unit.bind().get_unit_id()))
+            .collect();
+        self.pending_action = None;
+    }
+
+    // Turning back on part way through a phase picks up the units that haven't acted yet
+    #[func]
+    pub fn set_disabled(&mut self, disabled: bool) {
+        self.disabled = disabled;
+        self.unit_queue.clear();
+        self.pending_action = None;
+
+        if disabled { return; }
+        let (Some(field), Some(turn_manager)) = (&self.field, &self.turn_manager) else { return; };
+
+        let phase: CharType = field.bind().get_battle_state().turn.phase;
+        if phase != CharType::Player && !turn_manager.bind().is_battle_over() {
+            // Deferred like the turn manager's own call, the field may still be busy
+            self.base_mut().call_deferred("start_phase".into(), &[phase.to_variant()]);
+        }
+    }
+
+    // None if the unit isn't on the board any more
+    fn plan_unit_turn(&self, field: &FieldGripMap, unit: &Gd<FieldCharacter>) -> Option<AiPlan> {
+        let state: &BattleState = field.get_battle_state();
+        let unit: &UnitState = state.get_unit(unit.bind().get_unit_id())?;
+        let ranges: HashMap<u64, Rc<UnitRanges>> = field.get_all_char_ranges(); // Cached, so only the first plan after a move pays for these
+        let orders: AiOrders = AiOrders {
+            objective: self.has_objective.then_some(self.objective),
+            ..state.get_orders(unit)
+        };
+
+        Some(plan_turn(&*state.grid, unit, &state.units, &orders, &ranges))
+    }
+}
@@ -0,0 +1,118 @@
+use crate::nodes::{FieldCharacter, FieldGripMap, TurnManager};
+use crate::types::{read_save_value, BattleOutcome, CharType, Objectives};
+
+use godot::{builtin::{Callable, Dictionary, GString, PackedInt64Array, Variant}, classes::{object::ConnectFlags, INode, Node}, obj::{Base, Gd, WithBaseField}, prelude::{godot_api, GodotClass}};
+
+// Decides when the battle is won or lost, from the field's level or by rout without one
+#[derive(GodotClass)]
+#[class(base=Node)]
+pub struct BattleObjectives {
+    base: Base<Node>,
+    objectives: Objectives,
+    outcome: Option<BattleOutcome>,
+
+    #[export] field: Option<Gd<FieldGripMap>>,
+    #[export] turn_manager: Option<Gd<TurnManager>>,
+}
+
+#[godot_api]
+impl INode for BattleObjectives {
+    fn init(base: Base<Node>) -> Self {
+        Self {
+            base,
+            objectives: Objectives::default(),
+            outcome: None,
+
+            field: None,
+            turn_manager: None,
+        }
+    }
+
+    fn ready(&mut self) {
+        let objectives: Gd<BattleObjectives> = self.to_gd();
+
+        // Attacks and heals only matter through deaths, so these cover every action
+        // Deferred so the field and turn manager are done with whatever they were doing
+        if let Some(mut field) = self.field.clone() {
+            if let Some(level) = field.bind().level.clone() {
+                self.objectives = level.bind().get_objectives();
+            }
+
+            for (signal, method) in [("unit_moved", "on_unit_moved"), ("unit_died", "on_unit_died")] {
+                field.connect_ex(signal.into(), Callable::from_object_method(&objectives, method))
+                    .flags(ConnectFlags::DEFERRED.ord() as u32).done();
+            }
+        }
+
+        if let Some(turn_manager) = &mut self.turn_manager {
+            turn_manager.connect_ex("phase_started".into(), Callable::from_object_method(&objectives, "on_phase_started"))
+                .flags(ConnectFlags::DEFERRED.ord() as u32).done();
+        }
+    }
+}
+
+#[godot_api]
+impl BattleObjectives {
+    #[signal]
+    fn battle_won(reason: GString);
+
+    #[signal]
+    fn battle_lost(reason: GString);
+
+    #[func]
+    fn on_unit_moved(&mut self, _unit: Gd<FieldCharacter>) {
+        self.evaluate();
+    }
+
+    #[func]
+    fn on_unit_died(&mut self, _unit: Gd<FieldCharacter>) {
+        self.evaluate();
+    }
+
+    #[func]
+    fn on_phase_started(&mut self, _phase: CharType) {
+        self.evaluate();
+    }
+
+    // Check the objectives against the field as it is now, ending the battle if one is met
+    #[func]
+    pub fn evaluate(&mut self) {
+        if self.outcome.is_some() { return; }
+        let (Some(field), Some(mut turn_manager)) = (self.field.clone(), self.turn_manager.clone()) else { return; };
+
+        let Some(outcome) = self.objectives.evaluate(&field.bind().get_battle_state().get_objective_context()) else { return; };
+        self.outcome = Some(outcome.clone());
+
+        turn_manager.bind_mut().end_battle();
+
+        match outcome {
+            BattleOutcome::Won(reason) => {
+                self.base_mut().emit_signal("battle_won".into(), &[Variant::from(GString::from(reason))]);
+            }
+            BattleOutcome::Lost(reason) => {
+                self.base_mut().emit_signal("battle_lost".into(), &[Variant::from(GString::from(reason))]);
+            }
+        }
+    }
+
+    #[func]
+    pub fn is_battle_over(&self) -> bool {
+        self.outcome.is_some()
+    }
+
+    pub fn get_save_data(&self) -> Dictionary {
+        let progress: Vec<i64> = self.objectives.get_progress();
+
+        let mut data: Dictionary = Dictionary::new();
+        data.set("progress", PackedInt64Array::from(progress.as_slice()));
+        data
+    }
+
+    // Outcome isn't saved, a finished battle is just won or lost again on the next check
+    pub fn load_save_data(&mut self, data: &Dictionary) {
+        let progress: PackedInt64Array = read_save_value(data, "progress", PackedInt64Array::new());
+
+        self.objectives.set_progress(progress.as_slice());
+        self.outcome = None;
+    }
+}
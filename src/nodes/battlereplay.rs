@@ -0,0 +1,207 @@
+use crate::constants::*;
+use crate::nodes::{BattleAi, FieldGripMap};
+use crate::types::{read_save_value, ReplayLog, ReplayStep, REPLAY_STEP_LEN};
+
+use godot::{builtin::{Dictionary, GString, PackedInt32Array, PackedInt64Array, Variant}, classes::{file_access::ModeFlags, DirAccess, FileAccess, INode, InputEvent, Node}, meta::ToGodot, obj::{Base, Gd, GdRef, WithBaseField}, prelude::{godot_api, GodotClass}};
+
+// Writes out the field's replay log, and plays one back through the field one step at a time
+// Board checksums are compared before every step, so a replay that goes differently stops where it does
+#[derive(GodotClass)]
+#[class(base=Node)]
+pub struct BattleReplay {
+    base: Base<Node>,
+    playback: Option<ReplayLog>,
+    next_step: usize,
+    paused: bool,
+    step_requested: bool, // Run one step while paused
+
+    #[export] field: Option<Gd<FieldGripMap>>,
+    #[export] ai: Option<Gd<BattleAi>>,
+    #[export] quick_slot: GString, // Slot used by the replay actions
+}
+
+#[godot_api]
+impl INode for BattleReplay {
+    fn init(base: Base<Node>) -> Self {
+        Self {
+            base,
+            playback: None,
+            next_step: 0,
+            paused: false,
+            step_requested: false,
+
+            field: None,
+            ai: None,
+            quick_slot: GString::from("last"),
+        }
+    }
+
+    fn unhandled_input(&mut self, event: Gd<InputEvent>) {
+        if event.is_action_pressed("SaveReplayAction".into()) {
+            self.save_replay(self.quick_slot.clone());
+        } else if event.is_action_pressed("PlayReplayAction".into()) {
+            self.play_replay(self.quick_slot.clone());
+        } else if event.is_action_pressed("ReplayPauseAction".into()) {
+            let paused: bool = !self.paused;
+            self.set_paused(paused);
+        } else if event.is_action_pressed("ReplayStepAction".into()) {
+            self.step();
+        }
+    }
+
+    fn process(&mut self, _: f64) {
+        if self.playback.is_none() || (self.paused && !self.step_requested) { return; }
+        let Some(mut field) = self.field.clone() else { return; };
+
+        // Steps were only ever taken with everyone standing still
+        if field.bind().is_any_char_moving() { return; }
+
+        let playback: &ReplayLog = self.playback.as_ref().expect("Cannot fail due to above check");
+        let Some(step) = playback.steps.get(self.next_step).copied() else {
+            self.finish_playback();
+            self.base_mut().emit_signal("replay_finished".into(), &[]);
+            return;
+        };
+
+        let expected: u64 = playback.checksums[self.next_step];
+        let actual: u64 = field.bind().get_board_checksum();
+
+        if actual != expected || !field.bind_mut().apply_replay_step(step) {
+            let index: i64 = self.next_step as i64;
+            self.finish_playback();
+            self.base_mut().emit_signal("replay_desynced".into(), &[
+                Variant::from(index), Variant::from(expected as i64), Variant::from(actual as i64),
+            ]);
+            return;
+        }
+
+        self.next_step += 1;
+        self.step_requested = false;
+    }
+}
+
+#[godot_api]
+impl BattleReplay {
+    // Step that couldn't be played back the same, with the checksums before it
+    #[signal]
+    fn replay_desynced(step: i64, expected: i64, actual: i64);
+
+    #[signal]
+    fn replay_finished();
+
+    #[func]
+    pub fn get_replay_path(slot: GString) -> GString {
+        GString::from(format!("{}/{}.replay", REPLAY_DIR, slot))
+    }
+
+    // Returns false if there's nothing to save or the file couldn't be written
+    #[func]
+    pub fn save_replay(&mut self, slot: GString) -> bool {
+        let Some(field) = &self.field else { return false; };
+
+        let mut data: Dictionary = {
+            let field: GdRef<'_, FieldGripMap> = field.bind();
+            let Some(replay_log) = field.get_replay_log() else { return false; };
+
+            let mut steps: PackedInt32Array = PackedInt32Array::new();
+            for step in &replay_log.steps {
+                for value in step.to_ints() {
+                    steps.push(value);
+                }
+            }
+
+            let checksums: Vec<i64> = replay_log.checksums.iter().map(|checksum| *checksum as i64).collect();
+
+            let mut data: Dictionary = Dictionary::new();
+            data.set("seed", replay_log.seed as i64);
+            data.set("steps", steps);
+            data.set("checksums", PackedInt64Array::from(checksums.as_slice()));
+            data
+        };
+
+        data.set("version", REPLAY_VERSION);
+        data.set("scene", self.get_scene_path());
+
+        DirAccess::make_dir_recursive_absolute(REPLAY_DIR.into());
+
+        let Some(mut file) = FileAccess::open(Self::get_replay_path(slot), ModeFlags::WRITE) else { return false; };
+        file.store_var(data.to_variant());
+
+        true
+    }
+
+    // Start playing back a replay, which only works on a battle that hasn't been touched yet in the same scene
+    // Returns false if the replay is missing, broken or from a different version or scene, or if the battle has already begun
+    #[func]
+    pub fn play_replay(&mut self, slot: GString) -> bool {
+        let Some(mut field) = self.field.clone() else { return false; };
+
+        let Some(mut file) = FileAccess::open(Self::get_replay_path(slot), ModeFlags::READ) else { return false; };
+        let Ok(data) = file.get_var().try_to::<Dictionary>() else { return false; };
+
+        if read_save_value(&data, "version", 0i64) != REPLAY_VERSION { return false; }
+        if read_save_value(&data, "scene", GString::new()) != self.get_scene_path() { return false; }
+
+        let seed: u64 = read_save_value(&data, "seed", 0i64) as u64;
+        let ints: PackedInt32Array = read_save_value(&data, "steps", PackedInt32Array::new());
+        let checksums: PackedInt64Array = read_save_value(&data, "checksums", PackedInt64Array::new());
+
+        let steps: Option<Vec<ReplayStep>> = ints.as_slice().chunks(REPLAY_STEP_LEN).map(ReplayStep::from_ints).collect();
+        let Some(steps) = steps else { return false; };
+        if steps.len() != checksums.len() { return false; }
+
+        if !field.bind_mut().start_replay(seed) { return false; }
+
+        self.playback = Some(ReplayLog {
+            seed,
+            steps,
+            checksums: checksums.as_slice().iter().map(|checksum| *checksum as u64).collect(),
+        });
+        self.next_step = 0;
+        self.paused = false;
+        self.step_requested = false;
+
+        if let Some(ai) = &mut self.ai {
+            ai.bind_mut().set_disabled(true);
+        }
+
+        true
+    }
+
+    #[func]
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+    }
+
+    // Run the next step, only does anything while paused
+    #[func]
+    pub fn step(&mut self) {
+        if self.paused { self.step_requested = true; }
+    }
+
+    #[func]
+    pub fn is_playing(&self) -> bool {
+        self.playback.is_some()
+    }
+
+    // Hand the battle back to the player and AI from wherever playback got to
+    #[func]
+    pub fn finish_playback(&mut self) {
+        self.playback = None;
+
+        if let Some(field) = &mut self.field {
+            field.bind_mut().stop_replay();
+        }
+
+        if let Some(ai) = &mut self.ai {
+            ai.bind_mut().set_disabled(false);
+        }
+    }
+
+    fn get_scene_path(&self) -> GString {
+        self.base().get_tree()
+            .and_then(|tree| tree.get_current_scene())
+            .map(|scene| scene.get_scene_file_path())
+            .unwrap_or_default()
+    }
+}
@@ -1,128 +1,401 @@
-use crate::types::{VecTree, CharType};
+use crate::types::{read_save_value, AiBehaviour, AiOrders, CharType, MovementClass, MovementProfile, UnitState, UnitStats, WeaponRange, WeaponShape};
 use crate::nodes::FieldGripMap;
+use crate::resources::UnitDefinition;
 
-use godot::{builtin::Vector3i, classes::{CharacterBody3D, Engine, GridMap, ICharacterBody3D}, obj::{Base, WithBaseField}, prelude::{godot_api, GodotClass}};
+use std::collections::VecDeque;
+use godot::{builtin::{Dictionary, GString, StringName, Vector3, Vector3i}, classes::{CharacterBody3D, Engine, ICharacterBody3D, Node, PackedScene, Texture2D}, meta::ToGodot, obj::{Base, Gd, GdRef, WithBaseField}, prelude::{godot_api, GodotClass}, tools::try_load};
 
 #[derive(GodotClass)]
 #[class(base=CharacterBody3D)]
 pub struct FieldCharacter {
     base: Base<CharacterBody3D>,
-    pub movement_tree: VecTree<Vector3i>,
-    pub attack_tree: VecTree<Vector3i>,
-    pub heal_tree: VecTree<Vector3i>,
+    move_waypoints: VecDeque<Vector3>,
+    move_target: Option<Vector3i>,
+    move_fall_damage: i32, // Taken on arriving at move_target
+    unit_id: u64, // Handed out by the field in spawn order, 0 until it's on the field
+    pub home_position: Vector3i, // Where the unit started the battle
 
     // Create getters/setters
     #[export] #[var(get, set=set_field_pos)] pub field_position: Vector3i,
+    // Copied over the stats and ranges below on ready, overrides are then set by property name on top
+    #[export] pub definition: Option<Gd<UnitDefinition>>,
+    #[export] pub overrides: Dictionary,
     #[export] pub chartype: CharType,
+    #[export] #[var(get, set = set_movement_class)] pub movement_class: MovementClass,
+    // Set from the class, but can be changed per unit after the class is picked
+    #[export] pub climb_height: i32,
+    #[export] pub safe_drop: i32,
+    #[export] pub drop_height: i32,
     #[export] pub movement_range: u32,
-    #[export] pub attack_range: u32,
+    #[export] pub attack_range: u32, // Furthest the weapon reaches
+    #[export] pub attack_min_range: u32,
+    #[export] pub attack_vertical_range: u32, // Levels above or below that can be hit, 1 so melee still reaches a step up or down
+    #[export] pub attack_shape: WeaponShape,
     #[export] pub heal_range: u32,
+    #[export] pub move_speed: f32, // World units per second when following a path
+    #[export] pub max_hp: i32,
+    #[export] pub hp: i32, // Follows the field's battle state once the unit is on the field
+    #[export] pub attack: i32,
+    #[export] pub defence: i32,
+    #[export] pub accuracy: i32, // Out of 100, evasion is taken away from it
+    #[export] pub evasion: i32,
+    #[export] pub critical: i32, // Out of 100
+    #[export] pub heal_power: i32,
+    #[export] pub ai_behaviour: AiBehaviour,
+    #[export] pub guard_radius: u32, // Only for AiBehaviour::GuardArea, measured from where the unit starts
+    #[export] pub unit_group: i32, // Units sharing a group can have their danger zone shown together
+    #[export] pub unit_tag: GString, // Lets objectives refer to this unit, empty for none
 }
 
 #[godot_api]
 impl ICharacterBody3D for FieldCharacter {
     fn init(base: Base<CharacterBody3D>) -> Self {
+        let profile: MovementProfile = MovementClass::Infantry.get_profile();
+
         Self {
             base,
-            movement_tree: VecTree { value: Vector3i::new(0, 0, 0), children: vec![] },
-            attack_tree: VecTree { value: Vector3i::new(0, 0, 0), children: vec![] },
-            heal_tree: VecTree { value: Vector3i::new(0, 0, 0), children: vec![] },
+            move_waypoints: VecDeque::new(),
+            move_target: None,
+            move_fall_damage: 0,
+            unit_id: 0,
+            home_position: Vector3i::ZERO,
 
             field_position: Vector3i::ZERO,
+            definition: None,
+            overrides: Dictionary::new(),
             chartype: CharType::Enemy,
+            movement_class: MovementClass::Infantry,
+            climb_height: profile.climb_height,
+            safe_drop: profile.safe_drop,
+            drop_height: profile.drop_height,
             movement_range: 1,
             attack_range: 1,
+            attack_min_range: 1,
+            attack_vertical_range: 1,
+            attack_shape: WeaponShape::Diamond,
             heal_range: 0,
+            move_speed: 8.0,
+            max_hp: 10,
+            hp: 10,
+            attack: 3,
+            defence: 0,
+            accuracy: 90,
+            evasion: 10,
+            critical: 0,
+            heal_power: 0,
+            ai_behaviour: AiBehaviour::Aggressive,
+            guard_radius: 3,
+            unit_group: 0,
+            unit_tag: GString::new(),
+        }
+    }
+
+    // Before the field's ready, as children are readied first, so it only ever sees the final stats
+    fn ready(&mut self) {
+        self.apply_definition();
+        self.apply_overrides();
+    }
+
+    fn process(&mut self, delta: f64) {
+        if self.move_target == None { return; }
+
+        // Walk along waypoints, carrying leftover distance over to the next one
+        let mut position: Vector3 = self.base().get_position();
+        let mut step: f32 = self.move_speed * delta as f32;
+
+        while step > 0.0 {
+            let Some(waypoint) = self.move_waypoints.front().copied() else { break; };
+            let to_waypoint: Vector3 = waypoint - position;
+            let distance: f32 = to_waypoint.length();
+
+            if distance <= step {
+                position = waypoint;
+                step -= distance;
+                self.move_waypoints.pop_front();
+            } else {
+                position += to_waypoint / distance * step;
+                step = 0.0;
+            }
         }
+
+        self.base_mut().set_position(position);
+
+        if self.move_waypoints.is_empty() { self.finish_movement(); }
     }
 }
 
 #[godot_api]
 impl FieldCharacter {
+    #[signal]
+    fn movement_finished();
+
     // Function to have pos changes when remote debugging or in editor
     #[func]
     fn set_field_pos(&mut self, pos: Vector3i) {
         if Engine::singleton().is_editor_hint() || self.base().is_inside_tree() {
-            self.base().get_parent().expect("FieldCharacter should be child of FieldGridMap")
-                .try_cast::<FieldGripMap>().expect("FieldCharacter should be child of FieldGridMap")
-                .bind_mut().reposition_char_from_pos(self.get_field_position(), pos);
+            self.get_field().bind_mut().reposition_char(self.unit_id, pos);
         }
+
+        self.field_position = pos;
     }
 
-    // TODO: Calculate char ranges at point of movement for each field character and store it there
-    // TODO: Add vertical range for if attacking with ranged weapons or using thrusters to go up
-    // TODO: Add unit height restrictions so mechs are of height 2 for example
-    fn _get_range_tree(&self, field: &FieldGripMap, mut node: VecTree<Vector3i>, remaining_range: u32) -> VecTree<Vector3i> {
-        if remaining_range == 0 { return node; } // No more range to probe
-
-        // TODO: Handle slopes, cliffs, etc
-        // TODO: Make enemies block as well
-        // TODO: iterate over existing chars and remove them from being allowed for movement
-        // TODO: Handle being able to fall down a cliff 1 high w/o taking damge, can >1 high by taking damage
-        // TODO: Handle bounds/dropoff/etc so you can't move off the field
-        // Push the 4 tiles around the current one
-        for i in 0..4 {
-            let mut value: Vector3i = node.value;
-
-            // Adjust offsets to get tiles around current one
-            match i {
-                0 => value.x += 1,
-                1 => value.x -= 1,
-                2 => value.z += 1,
-                3 => value.z -= 1,
-                _ => {} // This case will never occur
-            };
-
-            let mut cell_item: i32 = field.base().get_cell_item(value);
-            
-            // Add ability to move up slopes
-            // TODO: Check orientation
-            let block_type_len: i32 = field.block_type_len;
-            let slope_index: i32 = field.slope_index;
-            if cell_item - (cell_item % block_type_len) == slope_index {
-                value.y += 1;
-            }
+    fn get_field(&self) -> Gd<FieldGripMap> {
+        self.base().get_parent().expect("FieldCharacter should be child of FieldGridMap")
+            .try_cast::<FieldGripMap>().expect("FieldCharacter should be child of FieldGridMap")
+    }
 
-            // Handle moving down
-            // TODO: handle damage on height > 1 drop
-            // TODO: MAKE SURE THIS ISN'T CALCULATED OUT OF FIELD BOUNDS!
-            while field.base().get_cell_item(value + Vector3i::new(0, -1, 0)) == GridMap::INVALID_CELL_ITEM {
-                value.y -= 1;
-            }
+    // Start moving through waypoints in world space, ending up at target on the field
+    // Fall damage is only taken once the character lands at the end
+    pub fn follow_path(&mut self, waypoints: Vec<Vector3>, target: Vector3i, fall_damage: i32) {
+        self.move_waypoints = waypoints.into();
+        self.move_target = Some(target);
+        self.move_fall_damage = fall_damage;
+    }
+
+    #[func]
+    pub fn is_moving(&self) -> bool {
+        self.move_target != None
+    }
+
+    // Field only finds out about the new position once the character gets there
+    fn finish_movement(&mut self) {
+        let Some(target) = self.move_target.take() else { return; };
+
+        self.set_field_pos(target);
+        self.hp = (self.hp - std::mem::take(&mut self.move_fall_damage)).max(0);
+        self.base_mut().emit_signal("movement_finished".into(), &[]);
+
+        // Deferred since the field can't bind this character while it's still moving
+        if !self.is_alive() {
+            let this: Gd<FieldCharacter> = self.to_gd();
+            self.get_field().call_deferred("remove_char".into(), &[this.to_variant()]);
+        }
+    }
+
+    // Picking a class resets the unit's climb and drop heights to the class defaults
+    #[func]
+    fn set_movement_class(&mut self, movement_class: MovementClass) {
+        let profile: MovementProfile = movement_class.get_profile();
 
-            cell_item = field.base().get_cell_item(value); // Get adjusted value
+        self.movement_class = movement_class;
+        self.climb_height = profile.climb_height;
+        self.safe_drop = profile.safe_drop;
+        self.drop_height = profile.drop_height;
+    }
+
+    // Copy the definition's values over this unit's, starting it on full hp
+    fn apply_definition(&mut self) {
+        let Some(definition) = self.definition.clone() else { return; };
+        let definition: GdRef<'_, UnitDefinition> = definition.bind();
 
-            // Cannot be on non-empty tiles
-            if cell_item == GridMap::INVALID_CELL_ITEM {
-                let child_node: VecTree<Vector3i> = VecTree::new(value, vec![]);
+        self.set_movement_class(definition.movement_class);
+        self.movement_range = definition.movement_range;
+        self.attack_range = definition.attack_range;
+        self.attack_min_range = definition.attack_min_range;
+        self.attack_vertical_range = definition.attack_vertical_range;
+        self.attack_shape = definition.attack_shape;
+        self.heal_range = definition.heal_range;
+        self.max_hp = definition.max_hp;
+        self.hp = definition.max_hp;
+        self.attack = definition.attack;
+        self.defence = definition.defence;
+        self.accuracy = definition.accuracy;
+        self.evasion = definition.evasion;
+        self.critical = definition.critical;
+        self.heal_power = definition.heal_power;
 
-                node.children.push(self._get_range_tree(field, child_node, remaining_range - 1));
+        // Stand in for whatever placeholder model the unit's scene came with
+        if let Some(model_scene) = &definition.model_scene {
+            if let Some(mut placeholder) = self.base().get_node_or_null("Model".into()) {
+                self.base_mut().remove_child(placeholder.clone());
+                placeholder.queue_free();
             }
+
+            let mut model: Gd<Node> = model_scene.instantiate().expect("Model scene should be instantiable");
+            model.set_name("Model".into());
+            self.base_mut().add_child(model);
         }
+    }
 
-        node
+    // Overrides go through the property setters, so e.g. movement_class still resets climb heights
+    fn apply_overrides(&mut self) {
+        let overrides: Dictionary = self.overrides.clone();
+
+        for (property, value) in overrides.iter_shared() {
+            let property: StringName = StringName::from(&property.stringify());
+            self.base_mut().set(property, value);
+        }
+    }
+
+    // Definition's name, or the node's when it has none
+    #[func]
+    pub fn get_display_name(&self) -> GString {
+        let name: Option<GString> = self.definition.as_ref()
+            .map(|definition| definition.bind().display_name.clone())
+            .filter(|name| !name.is_empty());
+
+        name.unwrap_or_else(|| GString::from(&self.base().get_name()))
+    }
+
+    #[func]
+    pub fn get_portrait(&self) -> Option<Gd<Texture2D>> {
+        self.definition.as_ref()?.bind().portrait.clone()
     }
 
-    pub fn get_range_tree(&self, field: &FieldGripMap, range: u32) -> VecTree<Vector3i> {
+    pub fn get_stats(&self) -> UnitStats {
+        UnitStats {
+            max_hp: self.max_hp,
+            hp: self.hp,
+            attack: self.attack,
+            defence: self.defence,
+            accuracy: self.accuracy,
+            evasion: self.evasion,
+            critical: self.critical,
+            heal_power: self.heal_power,
+        }
+    }
 
-        self._get_range_tree(&field, VecTree::new(self.field_position, vec![]), range)
+    #[func]
+    pub fn is_alive(&self) -> bool {
+        self.hp > 0
     }
 
-    // Make this find and return position in tree
-    fn _is_in_tree(&self, tree: &VecTree<Vector3i>, tree_coords: Vec<u32>, pos: Vector3i) -> Vec<u32> {
-        if tree.value == pos {
-            return tree_coords;
+    pub fn get_movement_profile(&self) -> MovementProfile {
+        MovementProfile {
+            climb_height: self.climb_height,
+            safe_drop: self.safe_drop,
+            drop_height: self.drop_height,
+            ..self.movement_class.get_profile()
         }
+    }
 
-        for i in 0..tree.children.len() {
-            let mut tree_coords: Vec<u32> = tree_coords.clone();
-            tree_coords.push(i as u32);
+    // Same every run of a level, unlike the instance id, so turn order comes out the same
+    pub fn get_unit_id(&self) -> u64 {
+        self.unit_id
+    }
 
-            let child_coords: Vec<u32> = self._is_in_tree(tree, tree_coords, pos);
+    pub fn set_unit_id(&mut self, unit_id: u64) {
+        self.unit_id = unit_id;
+    }
 
-            if !child_coords.is_empty() { return child_coords; }
+    pub fn get_weapon_range(&self) -> WeaponRange {
+        WeaponRange {
+            shape: self.attack_shape,
+            min_range: self.attack_min_range,
+            max_range: self.attack_range,
+            vertical_range: self.attack_vertical_range,
         }
+    }
 
-        return vec![];
+    pub fn get_unit_state(&self) -> UnitState {
+        UnitState {
+            id: self.get_unit_id(),
+            pos: self.field_position,
+            chartype: self.chartype,
+            class: self.movement_class,
+            profile: self.get_movement_profile(),
+            stats: self.get_stats(),
+            movement_range: self.movement_range,
+            weapon: self.get_weapon_range(),
+            heal_range: self.heal_range,
+            tag: self.unit_tag.to_string(),
+        }
+    }
+
+    // What the unit has been told to do when the AI takes it, BattleAi adds its own objective on top
+    pub fn get_ai_orders(&self) -> AiOrders {
+        AiOrders {
+            behaviour: self.ai_behaviour,
+            home: self.home_position,
+            guard_radius: self.guard_radius,
+            objective: None,
+        }
     }
-}
\ No newline at end of file
+
+    // Everything about the unit that can change in a battle, and what's needed to make it again
+    pub fn get_save_data(&self) -> Dictionary {
+        let definition_path: GString = self.definition.as_ref()
+            .map(|definition| definition.get_path())
+            .unwrap_or_default();
+
+        let mut data: Dictionary = Dictionary::new();
+        data.set("name", GString::from(&self.base().get_name()));
+        data.set("scene", self.base().get_scene_file_path());
+        data.set("definition", definition_path);
+        data.set("unit_id", self.unit_id as i64);
+        data.set("field_position", self.field_position);
+        data.set("home_position", self.home_position);
+        data.set("chartype", self.chartype);
+        data.set("movement_class", self.movement_class);
+        data.set("climb_height", self.climb_height);
+        data.set("safe_drop", self.safe_drop);
+        data.set("drop_height", self.drop_height);
+        data.set("movement_range", self.movement_range);
+        data.set("attack_range", self.attack_range);
+        data.set("attack_min_range", self.attack_min_range);
+        data.set("attack_vertical_range", self.attack_vertical_range);
+        data.set("attack_shape", self.attack_shape);
+        data.set("heal_range", self.heal_range);
+        data.set("move_speed", self.move_speed);
+        data.set("max_hp", self.max_hp);
+        data.set("hp", self.hp);
+        data.set("attack", self.attack);
+        data.set("defence", self.defence);
+        data.set("accuracy", self.accuracy);
+        data.set("evasion", self.evasion);
+        data.set("critical", self.critical);
+        data.set("heal_power", self.heal_power);
+        data.set("ai_behaviour", self.ai_behaviour);
+        data.set("guard_radius", self.guard_radius);
+        data.set("unit_group", self.unit_group);
+        data.set("unit_tag", self.unit_tag.clone());
+        data
+    }
+
+    // New unit from its saved scene and definition, load_save_data still needs calling once it's ready
+    pub fn from_save_data(data: &Dictionary) -> Option<Gd<FieldCharacter>> {
+        let scene_path: GString = read_save_value(data, "scene", GString::new());
+        let definition_path: GString = read_save_value(data, "definition", GString::new());
+
+        let mut char: Gd<FieldCharacter> = try_load::<PackedScene>(scene_path).ok()?
+            .instantiate()?
+            .try_cast::<FieldCharacter>().ok()?;
+
+        let name: GString = read_save_value(data, "name", GString::from("FieldCharacter"));
+        char.set_name(name.into());
+        char.bind_mut().definition = try_load::<UnitDefinition>(definition_path).ok();
+
+        Some(char)
+    }
+
+    // Set directly rather than through setters, the field takes care of the position itself
+    pub fn load_save_data(&mut self, data: &Dictionary) {
+        self.unit_id = read_save_value(data, "unit_id", self.unit_id as i64) as u64;
+        self.field_position = read_save_value(data, "field_position", self.field_position);
+        self.home_position = read_save_value(data, "home_position", self.home_position);
+        self.chartype = read_save_value(data, "chartype", self.chartype);
+        self.movement_class = read_save_value(data, "movement_class", self.movement_class);
+        self.climb_height = read_save_value(data, "climb_height", self.climb_height);
+        self.safe_drop = read_save_value(data, "safe_drop", self.safe_drop);
+        self.drop_height = read_save_value(data, "drop_height", self.drop_height);
+        self.movement_range = read_save_value(data, "movement_range", self.movement_range);
+        self.attack_range = read_save_value(data, "attack_range", self.attack_range);
+        self.attack_min_range = read_save_value(data, "attack_min_range", self.attack_min_range);
+        self.attack_vertical_range = read_save_value(data, "attack_vertical_range", self.attack_vertical_range);
+        self.attack_shape = read_save_value(data, "attack_shape", self.attack_shape);
+        self.heal_range = read_save_value(data, "heal_range", self.heal_range);
+        self.move_speed = read_save_value(data, "move_speed", self.move_speed);
+        self.max_hp = read_save_value(data, "max_hp", self.max_hp);
+        self.hp = read_save_value(data, "hp", self.hp);
+        self.attack = read_save_value(data, "attack", self.attack);
+        self.defence = read_save_value(data, "defence", self.defence);
+        self.accuracy = read_save_value(data, "accuracy", self.accuracy);
+        self.evasion = read_save_value(data, "evasion", self.evasion);
+        self.critical = read_save_value(data, "critical", self.critical);
+        self.heal_power = read_save_value(data, "heal_power", self.heal_power);
+        self.ai_behaviour = read_save_value(data, "ai_behaviour", self.ai_behaviour);
+        self.guard_radius = read_save_value(data, "guard_radius", self.guard_radius);
+        self.unit_group = read_save_value(data, "unit_group", self.unit_group);
+        self.unit_tag = read_save_value(data, "unit_tag", self.unit_tag.clone());
+    }
+}
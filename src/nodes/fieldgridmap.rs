@@ -1,9 +1,11 @@
 use crate::nodes::PanningCamera;
 use crate::nodes::FieldCharacter;
-use crate::types::VecTree;
+use crate::nodes::TurnManager;
+use crate::resources::{LevelDefinition, ReinforcementWave, UnitSpawn};
+use crate::types::{read_save_value, forecast_exchange, get_strike_context, ActionLog, AiOrders, AttackResult, BattleCommand, BattleGrid, BattleOutcome, BattleRng, BattleState, CharType, ExchangeForecast, FieldCells, HighlightLayer, HighlightLayers, MovementClass, Objectives, Occupants, PHASE_ORDER, ReachMap, RangeCache, ReplayLog, ReplayStep, SimResult, StrikeContext, TerrainCosts, TurnState, UnitRanges, UnitState};
 
-use std::collections::HashMap;
-use godot::{builtin::{Array, Vector3, Vector3i}, classes::{GridMap, IGridMap, InputEvent, InputEventMouseButton, Node}, global::MouseButton, obj::{Base, Gd, GdMut, WithBaseField}, prelude::{godot_api, GodotClass}};
+use std::{cell::RefCell, collections::{HashMap, HashSet}, rc::Rc};
+use godot::{builtin::{Array, Dictionary, PackedInt32Array, Variant, Vector3, Vector3i}, classes::{GridMap, IGridMap, InputEvent, InputEventMouseButton, Node, PackedScene}, global::MouseButton, meta::ToGodot, obj::{Base, Gd, GdMut, GdRef, WithBaseField}, prelude::{godot_api, GodotClass}};
 
 
 // TODO: make your own
@@ -12,18 +14,44 @@ use godot::{builtin::{Array, Vector3, Vector3i}, classes::{GridMap, IGridMap, In
 pub struct FieldGripMap {
     base: Base<GridMap>,
     last_mouse_coords: Option<Vector3i>,
-    last_highlight_cell_offset: i32,
-    char_refs: HashMap<Vector3i, Gd<FieldCharacter>>,
+    hovered_char: Option<Gd<FieldCharacter>>,
+    char_refs: HashMap<u64, Gd<FieldCharacter>>, // By unit id, each shows the unit of the same id in state
     focused_char: Option<Gd<FieldCharacter>>,
-    focus_highlighted_cells: Vec<Vector3i>, // TODO: Make this a hashmap or tree?
+    highlights: HighlightLayers, // Only ever drawn on the overlay, terrain is never touched
+    focused_ranges: Option<Rc<UnitRanges>>,
+    path_preview_cost: i32,
+    state: BattleState, // Everything the rules know about the battle, the nodes only show it
+    next_unit_id: u64, // Given to the next unit put on the field, counting from 1 in spawn order
+    board_version: u64, // Bumped on anything that could change a unit's ranges
+    range_caches: RefCell<HashMap<u64, RangeCache>>, // By unit id, filled in as ranges are asked for
+    danger_zone_group: Option<i32>, // Group whose danger zone is shown, -1 for every enemy
+    danger_zone_version: Option<u64>, // Board version the danger zone was last worked out for
+    action_log: ActionLog, // Commands given this phase
+    rewind_point: Option<Dictionary>, // Field as the player's phase began
+    replay_log: Option<ReplayLog>, // None once the battle can't be replayed from its seed
+    replaying: bool, // Orders only come from the replay while it plays
 
     #[export] pub cam: Option<Gd<PanningCamera>>,
-    #[export] pub highlight_offset: i32,
+    #[export] pub turn_manager: Option<Gd<TurnManager>>,
+    #[export] pub overlay: Option<Gd<GridMap>>, // Same mesh library and cells as the field, drawn over it
+    #[export] pub level: Option<Gd<LevelDefinition>>, // Blocks and units to start with, None to use what's in the scene
+    #[export] pub highlight_offset: i32, // Highlighted version of a block is its item plus the offset
     #[export] pub highlight_move_offset: i32,
     #[export] pub highlight_attack_offset: i32,
     #[export] pub highlight_heal_offset: i32,
-    #[export] pub block_type_len: i32,
+    #[export] pub highlight_fall_offset: i32, // Cells that can be moved to by taking fall damage
+    #[export] pub highlight_path_offset: i32,
+    #[export] pub highlight_path_end_offset: i32,
+    #[export] pub highlight_danger_offset: i32,
     #[export] pub slope_index: i32,
+    #[export] pub stair_index: i32, // -1 if the field has no stairs
+    #[export] pub ramp_ascend_dir: Vector3i, // Low to high edge of slopes and stairs with no rotation
+    #[export] #[var(get, set = set_terrain_costs)] pub terrain_costs: Dictionary, // Base item -> cost, negative is impassable
+    #[export] #[var(get, set = set_class_terrain_costs)] pub class_terrain_costs: Dictionary, // Class name -> overrides of terrain_costs
+    #[export] pub fall_damage_per_level: i32, // Damage for each level fallen past a unit's safe drop
+    #[export] #[var(get, set = set_terrain_defence)] pub terrain_defence: Dictionary, // Base item -> defence bonus for units standing on it
+    #[export] pub rng_seed: i64, // Same seed and same actions give the same battle
+    #[export] pub allow_rewind: bool, // Difficulty setting, lets the player start their phase over
 }
 
 #[godot_api]
@@ -32,38 +60,107 @@ impl IGridMap for FieldGripMap {
         Self {
             base,
             last_mouse_coords: None,
-            last_highlight_cell_offset: -1,
+            hovered_char: None,
             char_refs: HashMap::new(),
             focused_char: None,
-            focus_highlighted_cells: Vec::new(),
+            highlights: HighlightLayers::new(),
+            focused_ranges: None,
+            path_preview_cost: -1,
+            state: BattleState::new(Rc::new(BattleGrid::new()), 0),
+            next_unit_id: 1,
+            board_version: 0,
+            range_caches: RefCell::new(HashMap::new()),
+            danger_zone_group: None,
+            danger_zone_version: None,
+            action_log: ActionLog::new(),
+            rewind_point: None,
+            replay_log: None,
+            replaying: false,
 
             cam: None,
+            turn_manager: None,
+            overlay: None,
+            level: None,
             highlight_offset: 0,
             highlight_move_offset: 0,
             highlight_attack_offset: 0,
             highlight_heal_offset: 0,
-            block_type_len: 0,
+            highlight_fall_offset: 0,
+            highlight_path_offset: 0,
+            highlight_path_end_offset: 0,
+            highlight_danger_offset: 0,
             slope_index: 0,
+            stair_index: -1,
+            ramp_ascend_dir: Vector3i::LEFT, // Slope prism meshes are tallest on the left
+            terrain_costs: Dictionary::new(),
+            class_terrain_costs: Dictionary::new(),
+            fall_damage_per_level: 0,
+            rng_seed: 0,
+            terrain_defence: Dictionary::new(),
+            allow_rewind: false,
         }
     }
 
     fn ready(&mut self) {
+        let level: Option<Gd<LevelDefinition>> = self.level.clone();
+
+        if let Some(grid_scene) = level.as_ref().and_then(|level| level.bind().grid_scene.clone()) {
+            self.load_grid(&grid_scene);
+        }
+
+        self.sync_grid();
+        self.state.rng = BattleRng::new(self.rng_seed as u64);
+        self.state.fall_damage_per_level = self.fall_damage_per_level;
+        self.replay_log = Some(ReplayLog::new(self.rng_seed as u64));
+
         // Set positions of all child characters
         let children: Array<Gd<Node>> = self.base().get_children();
         for char in children.iter_shared() {
             if char.get_class() == "FieldCharacter".into() {
-                let mut char: Gd<FieldCharacter> = char.cast::<FieldCharacter>();
-                let field_pos: Vector3i = char.bind().get_field_position();
+                self.register_char(char.cast::<FieldCharacter>());
+            }
+        }
 
-                char.set_position(self.get_world_pos_from_coords(field_pos));
+        // Level's units go in after any placed in the scene, and never on top of them
+        if let Some(level) = level {
+            for chartype in PHASE_ORDER {
+                let spawns: Array<Gd<UnitSpawn>> = level.bind().get_spawns(chartype);
 
-                self.char_refs.insert(field_pos, char);
+                for spawn in spawns.iter_shared() {
+                    self.spawn_char(&spawn, chartype);
+                }
             }
         }
     }
 
     fn unhandled_input(&mut self, event: Gd<InputEvent>) {
-        if event.get_class() == "InputEventMouseButton".into() {
+        // Board can't change under a character while it's walking
+        if self.is_any_char_moving() { return; }
+
+        // Looking at the danger zone is fine whoever's phase it is
+        if event.is_action_pressed("DangerZoneAction".into()) {
+            self.toggle_danger_zone();
+            return;
+        }
+
+        if self.replaying { return; }
+
+        // Only take orders on the player's phase
+        if !self.is_player_phase() { return; }
+
+        if event.is_action_pressed("WaitAction".into()) {
+            if let Some(focused_char) = self.focused_char.clone() {
+                self.wait_char(focused_char);
+            }
+        } else if event.is_action_pressed("UndoAction".into()) {
+            self.undo_command();
+        } else if event.is_action_pressed("RedoAction".into()) {
+            self.redo_command();
+        } else if event.is_action_pressed("RewindAction".into()) {
+            self.rewind_turn();
+        } else if event.is_action_pressed("EndPhaseAction".into()) {
+            self.end_player_phase();
+        } else if event.get_class() == "InputEventMouseButton".into() {
             let event: Gd<InputEventMouseButton> = event.cast(); // Cast won't fail due to above check
 
             if event.get_button_index() == MouseButton::LEFT && event.is_pressed() && self.focused_char == None {
@@ -74,13 +171,15 @@ impl IGridMap for FieldGripMap {
 
                     pos.y += 1; // Block above currently moused
 
-                    // Get currently moused over character
-                    if let Some(char_ref) = self.char_refs.get(&pos) {
-                        // Since movement range doesn't include the current position, add 1
-                        move_range = char_ref.bind().get_movement_range() + 1;
-                        attack_range = char_ref.bind().get_attack_range();
-                        heal_range = char_ref.bind().get_heal_range();
-                        self.set_char_focused(Some(char_ref.clone()));
+                    // Get currently moused over character, if it can be given orders
+                    if let Some(char_ref) = self.get_char_at(pos) {
+                        if self.can_control_char(&char_ref) {
+                            // Since movement range doesn't include the current position, add 1
+                            move_range = self.get_char_move_range(&char_ref) + 1;
+                            attack_range = char_ref.bind().get_attack_range();
+                            heal_range = char_ref.bind().get_heal_range();
+                            self.set_char_focused(Some(char_ref));
+                        }
                     }
 
                     if move_range > 0 || attack_range > 0 || heal_range > 0 {
@@ -102,32 +201,46 @@ impl IGridMap for FieldGripMap {
                 }
             } else if event.get_button_index() == MouseButton::LEFT && event.is_pressed() && self.focused_char != None {
                 // Move char where appropriate
-                if let Some(pos) = self.last_mouse_coords {
-                    let mut focused_char: GdMut<'_, FieldCharacter> = self.focused_char.as_mut().expect("Always Some").bind_mut();
-                    // TODO: focused_char.set_field_pos_dir(self, pos);
+                if let Some(mut pos) = self.last_mouse_coords {
+                    pos.y += 1; // Block above currently moused
+
+                    let focused_char: Gd<FieldCharacter> = self.focused_char.clone().expect("Cannot fail due to above check");
+
+                    // Clicking on another unit attacks or heals it instead of moving
+                    if let Some(target) = self.get_char_at(pos) {
+                        if target != focused_char {
+                            if focused_char.bind().chartype.is_hostile_to(target.bind().chartype) {
+                                self.attack_char(focused_char, pos);
+                            } else {
+                                self.heal_char(focused_char, pos);
+                            }
+                        }
+                    } else if self.move_char(focused_char, pos) {
+                        self.set_char_focused(None);
+                        self.clear_char_ranges();
+                    }
                 }
             }
         }
     }
 
     fn process(&mut self, _: f64) {
+        // Enemies are only looked at again once everyone has stopped moving
+        if self.danger_zone_group.is_some() && self.danger_zone_version != Some(self.board_version) && !self.is_any_char_moving() {
+            self.update_danger_zone();
+        }
+
         // Mouse pos is calculated every frame for smoothness
         if let Some(cam) = self.get_cam() {
             if let Some(world_pos) = cam.bind().get_world_mouse_pos_option() {
                 let mouse_coords: Vector3i = self.get_coords_from_world_pos(world_pos);
 
                 if Some(mouse_coords) != self.last_mouse_coords {
-                    if self.focused_char == None { self.last_highlight_cell_offset = 0; } // Block offset can only be 0 if char not focused
-
-                    if let Some(last_mouse_coords) = self.last_mouse_coords {
-                        self.set_overlay_block(last_mouse_coords, self.last_highlight_cell_offset);
-                    }
-
-                    self.last_highlight_cell_offset = self.base().get_cell_item(mouse_coords) % self.block_type_len;
-
-                    self.set_overlay_block(mouse_coords, self.highlight_offset);
-
                     self.last_mouse_coords = Some(mouse_coords);
+
+                    self.set_highlight_layer(HighlightLayer::Hover, [mouse_coords]);
+                    self.update_path_preview();
+                    self.update_hover(mouse_coords);
                 }
             }
         }
@@ -136,6 +249,37 @@ impl IGridMap for FieldGripMap {
 
 #[godot_api]
 impl FieldGripMap {
+    #[signal]
+    fn path_preview_changed(cost: i32);
+
+    #[signal]
+    fn unit_attacked(attacker: Gd<FieldCharacter>, defender: Gd<FieldCharacter>, hit: bool, crit: bool, damage: i32);
+
+    #[signal]
+    fn unit_healed(healer: Gd<FieldCharacter>, target: Gd<FieldCharacter>, amount: i32);
+
+    #[signal]
+    fn unit_died(unit: Gd<FieldCharacter>);
+
+    #[signal]
+    fn unit_moved(unit: Gd<FieldCharacter>);
+
+    #[signal]
+    fn unit_hovered(unit: Gd<FieldCharacter>);
+
+    #[signal]
+    fn unit_unhovered();
+
+    #[signal]
+    fn unit_focused(unit: Gd<FieldCharacter>);
+
+    #[signal]
+    fn unit_unfocused();
+
+    // Sent whenever the mouse moves onto another block
+    #[signal]
+    fn cell_hovered(coords: Vector3i, terrain_info: Dictionary);
+
     #[func]
     pub fn get_coords_from_world_pos(&self, world_pos: Vector3) -> Vector3i {
         let local_pos: Vector3 = self.base().to_local(world_pos);
@@ -148,16 +292,331 @@ impl FieldGripMap {
         self.base().to_global(local_pos)
     }
 
+    pub fn get_highlight_offset(&self, layer: HighlightLayer) -> i32 {
+        match layer {
+            HighlightLayer::Danger => self.highlight_danger_offset,
+            HighlightLayer::Heal => self.highlight_heal_offset,
+            HighlightLayer::Attack => self.highlight_attack_offset,
+            HighlightLayer::Move => self.highlight_move_offset,
+            HighlightLayer::Fall => self.highlight_fall_offset,
+            HighlightLayer::Path => self.highlight_path_offset,
+            HighlightLayer::PathEnd => self.highlight_path_end_offset,
+            HighlightLayer::Hover => self.highlight_offset,
+        }
+    }
+
+    // Replace every cell on one layer, redrawing only what changed
+    pub fn set_highlight_layer(&mut self, layer: HighlightLayer, cells: impl IntoIterator<Item = Vector3i>) {
+        for pos in self.highlights.set_layer(layer, cells) {
+            self.refresh_overlay_block(pos);
+        }
+    }
+
+    // Redraw the overlay over a block with the highlight it should currently have
+    pub fn refresh_overlay_block(&mut self, coords: Vector3i) {
+        let cell_item: i32 = self.base().get_cell_item(coords);
+        let orientation: i32 = self.base().get_cell_item_orientation(coords);
+
+        // Highlighting air would create a block
+        let overlay_item: i32 = match self.highlights.get_top(coords) {
+            Some(layer) if cell_item != GridMap::INVALID_CELL_ITEM => cell_item + self.get_highlight_offset(layer),
+            _ => GridMap::INVALID_CELL_ITEM,
+        };
+
+        let Some(overlay) = &mut self.overlay else { return; };
+
+        // Always preserve orientation so slopes line up
+        overlay.set_cell_item_ex(coords, overlay_item).orientation(orientation).done();
+    }
+
+    // Parse the table when set so lookups during pathing are cheap
+    #[func]
+    pub fn set_terrain_costs(&mut self, terrain_costs: Dictionary) {
+        Rc::make_mut(&mut self.state.grid).terrain_costs = TerrainCosts::from_dictionary(&terrain_costs);
+        self.terrain_costs = terrain_costs;
+        self.invalidate_ranges();
+    }
+
+    #[func]
+    pub fn set_class_terrain_costs(&mut self, class_terrain_costs: Dictionary) {
+        let class_costs: &mut HashMap<MovementClass, TerrainCosts> = &mut Rc::make_mut(&mut self.state.grid).class_costs;
+        class_costs.clear();
+
+        for (class, costs) in class_terrain_costs.iter_shared() {
+            let (Ok(class), Ok(costs)) = (class.try_to::<MovementClass>(), costs.try_to::<Dictionary>()) else { continue; };
+
+            class_costs.insert(class, TerrainCosts::from_dictionary(&costs));
+        }
+
+        self.class_terrain_costs = class_terrain_costs;
+        self.invalidate_ranges();
+    }
+
+    // Cost of stepping onto the block at coords, None if impassable
+    // Class overrides take priority over the field's table
+    pub fn get_terrain_cost_option(&self, coords: Vector3i, class: MovementClass) -> Option<u32> {
+        self.state.grid.get_terrain_cost_for(coords, class)
+    }
+
+    // Function meant for godot, -1 if the block is impassable
+    #[func]
+    pub fn get_terrain_cost(&self, coords: Vector3i, class: MovementClass) -> i32 {
+        self.get_terrain_cost_option(coords, class).map_or(-1, |cost| cost as i32)
+    }
+
+    #[func]
+    pub fn set_terrain_defence(&mut self, terrain_defence: Dictionary) {
+        let terrain_defence_table: &mut HashMap<i32, i32> = &mut Rc::make_mut(&mut self.state.grid).terrain_defence;
+        terrain_defence_table.clear();
+
+        for (base_item, bonus) in terrain_defence.iter_shared() {
+            let (Ok(base_item), Ok(bonus)) = (base_item.try_to::<i32>(), bonus.try_to::<i32>()) else { continue; };
+
+            terrain_defence_table.insert(base_item, bonus);
+        }
+
+        self.terrain_defence = terrain_defence;
+    }
+
+    // Defence bonus for a unit standing at coords
+    #[func]
+    pub fn get_terrain_defence_at(&self, coords: Vector3i) -> i32 {
+        self.get_terrain_defence(coords + Vector3i::DOWN)
+    }
+
+    // Height, terrain and cover modifiers for a strike by striker from one standing cell at another
+    pub fn get_strike_context(&self, striker: &Gd<FieldCharacter>, from: Vector3i, target: Vector3i) -> StrikeContext {
+        get_strike_context(self, from, striker.bind().get_movement_profile().height, target)
+    }
+
+    // Whether defender could strike back at a unit attacking from from_cell
+    pub fn can_counter(&self, defender: &Gd<FieldCharacter>, from_cell: Vector3i) -> bool {
+        defender.bind().attack_range > 0 && self.is_in_char_attack_map(defender, from_cell)
+    }
+
+    // Everything about an attack before committing to it, for UI to show
+    // Resulting hp assumes every strike hits without a crit
+    #[func]
+    pub fn forecast_attack(&self, attacker: Gd<FieldCharacter>, defender: Gd<FieldCharacter>, from_cell: Vector3i) -> Dictionary {
+        let defender_pos: Vector3i = defender.bind().field_position;
+        let context: StrikeContext = self.get_strike_context(&attacker, from_cell, defender_pos);
+        let counter_context: Option<StrikeContext> = self.can_counter(&defender, from_cell)
+            .then(|| self.get_strike_context(&defender, defender_pos, from_cell));
+
+        let forecast: ExchangeForecast = forecast_exchange(
+            &attacker.bind().get_stats(), &defender.bind().get_stats(), &context, counter_context.as_ref(),
+        );
+
+        let mut dict: Dictionary = Dictionary::new();
+        dict.set("damage", forecast.attack.damage);
+        dict.set("hit_chance", forecast.attack.hit_chance);
+        dict.set("crit_chance", forecast.attack.crit_chance);
+        dict.set("expected_damage", forecast.attack.get_expected_damage());
+        dict.set("can_counter", forecast.counter.is_some());
+
+        if let Some(counter) = forecast.counter {
+            dict.set("counter_damage", counter.damage);
+            dict.set("counter_hit_chance", counter.hit_chance);
+            dict.set("counter_crit_chance", counter.crit_chance);
+            dict.set("counter_expected_damage", counter.get_expected_damage());
+        }
+
+        dict.set("attacker_hp", attacker.bind().hp);
+        dict.set("defender_hp", defender.bind().hp);
+        dict.set("attacker_hp_after", forecast.attacker_hp);
+        dict.set("defender_hp_after", forecast.defender_hp);
+        dict.set("height_advantage", context.height_advantage);
+        dict.set("terrain_defence", context.terrain_defence);
+        dict.set("cover", context.cover.to_variant());
+
+        dict
+    }
+
+    // Copy every block and the ramp settings into the grid the rules read
+    // Bounds are taken from used cells so searches never leave the map
+    // Needs to be called whenever the GridMap is changed other than through set_field_cell
+    #[func]
+    pub fn sync_grid(&mut self) {
+        let used_cells: Array<Vector3i> = self.base().get_used_cells();
+        let cells: Vec<(Vector3i, i32, i32)> = used_cells.iter_shared()
+            .map(|cell| (cell, self.base().get_cell_item(cell), self.base().get_cell_item_orientation(cell)))
+            .collect();
+
+        let (slope_index, stair_index, ramp_ascend_dir): (i32, i32, Vector3i) = (self.slope_index, self.stair_index, self.ramp_ascend_dir);
+        let grid: &mut BattleGrid = Rc::make_mut(&mut self.state.grid);
+        grid.slope_index = slope_index;
+        grid.stair_index = stair_index;
+        grid.ramp_ascend_dir = ramp_ascend_dir;
+
+        grid.clear();
+        for (cell, item, orientation) in cells {
+            grid.set_cell(cell, item, orientation);
+        }
+
+        self.invalidate_ranges();
+    }
+
+    // Place a block and keep the grid and ranges up to date
+    // Use instead of set_cell_item for anything but highlights
+    #[func]
+    pub fn set_field_cell(&mut self, coords: Vector3i, item: i32, orientation: i32) {
+        self.base_mut().set_cell_item_ex(coords, item).orientation(orientation).done();
+
+        let grid: &mut BattleGrid = Rc::make_mut(&mut self.state.grid);
+        grid.set_cell(coords, item, orientation);
+        grid.update_bounds();
+
+        self.invalidate_ranges();
+        self.refresh_overlay_block(coords);
+    }
+
+    // Throw away every unit's cached ranges, for board changes the field can't see
     #[func]
-    pub fn set_overlay_block(&mut self, overlay_coords: Vector3i, highlight_offset: i32) {
-        let mut cell_type: i32 = self.base().get_cell_item(overlay_coords);
-        cell_type -= cell_type % self.block_type_len;
+    pub fn invalidate_ranges(&mut self) {
+        self.board_version += 1;
+    }
+
+    // Cached ranges of a unit, worked out again if the board or the unit changed
+    // Kept on the field rather than the character, so asking never needs to bind it mutably
+    pub fn get_unit_ranges(&self, unit: &UnitState) -> Rc<UnitRanges> {
+        let move_range: u32 = self.state.get_move_range(unit);
+
+        let cached: Option<Rc<UnitRanges>> = self.range_caches.borrow().get(&unit.id)
+            .and_then(|cache| cache.get(self.board_version, unit, move_range));
+        if let Some(ranges) = cached { return ranges; }
+
+        let ranges: Rc<UnitRanges> = Rc::new(UnitRanges::build(&*self.state.grid, unit, move_range, &self.state.get_occupants()));
+
+        self.range_caches.borrow_mut().insert(unit.id, RangeCache {
+            board_version: self.board_version,
+            unit: unit.clone(),
+            move_range,
+            ranges: ranges.clone(),
+        });
+
+        ranges
+    }
+
+    // Ranges of the unit a character shows
+    // One already taken off the board, like while it falls to its death, gets what it could reach from where it stood
+    pub fn get_char_ranges(&self, char: &Gd<FieldCharacter>) -> Rc<UnitRanges> {
+        let id: u64 = char.bind().get_unit_id();
+
+        match self.state.get_unit(id) {
+            Some(unit) => self.get_unit_ranges(unit),
+            None => Rc::new(UnitRanges::build(&*self.state.grid, &char.bind().get_unit_state(), 0, &self.state.get_occupants())),
+        }
+    }
+
+    // Ranges of every unit on the board by id
+    pub fn get_all_char_ranges(&self) -> HashMap<u64, Rc<UnitRanges>> {
+        self.state.units.iter()
+            .map(|unit| (unit.id, self.get_unit_ranges(unit)))
+            .collect()
+    }
 
-        // Always preserve orientation
-        let orientation: i32 = self.base().get_cell_item_orientation(overlay_coords);
+    // Work out ranges ahead of time so the phase's first clicks and plans don't have to
+    #[func]
+    pub fn precompute_ranges(&mut self, _phase: CharType) {
+        for unit in &self.state.units {
+            self.get_unit_ranges(unit);
+        }
 
-        self.base_mut().set_cell_item_ex(overlay_coords, cell_type + highlight_offset)
-            .orientation(orientation).done();
+        // Who has already moved changes with the phase, and with it how far enemies reach
+        self.danger_zone_version = None;
+    }
+
+    // Show every cell the enemies in group could attack next phase, -1 for every enemy
+    #[func]
+    pub fn show_danger_zone(&mut self, group: i32) {
+        self.danger_zone_group = Some(group);
+        self.update_danger_zone();
+    }
+
+    #[func]
+    pub fn hide_danger_zone(&mut self) {
+        self.danger_zone_group = None;
+        self.danger_zone_version = None;
+        self.set_highlight_layer(HighlightLayer::Danger, []);
+    }
+
+    // Hovering an enemy shows just its group, anywhere else shows every enemy
+    #[func]
+    pub fn toggle_danger_zone(&mut self) {
+        if self.danger_zone_group.is_some() {
+            self.hide_danger_zone();
+            return;
+        }
+
+        let hovered_group: Option<i32> = self.last_mouse_coords
+            .and_then(|coords| self.get_char_at(coords + Vector3i::UP))
+            .filter(|char_ref| char_ref.bind().chartype == CharType::Enemy)
+            .map(|char_ref| char_ref.bind().unit_group);
+
+        self.show_danger_zone(hovered_group.unwrap_or(-1));
+    }
+
+    #[func]
+    pub fn is_danger_zone_shown(&self) -> bool {
+        self.danger_zone_group.is_some()
+    }
+
+    // Union of the attack coverage of every enemy in the shown group
+    fn update_danger_zone(&mut self) {
+        let Some(group) = self.danger_zone_group else { return; };
+
+        let mut danger_cells: HashSet<Vector3i> = HashSet::new();
+        for char_ref in self.get_chars_of_type(CharType::Enemy) {
+            if group != -1 && char_ref.bind().unit_group != group { continue; }
+
+            danger_cells.extend(self.get_char_ranges(&char_ref).attack_coverage.iter().copied());
+        }
+
+        self.danger_zone_version = Some(self.board_version);
+        self.show_cells(danger_cells, HighlightLayer::Danger);
+    }
+
+    // Damage a character would take from falls when moving to coords, 0 if it can't get there
+    #[func]
+    pub fn get_fall_damage(&self, char: Gd<FieldCharacter>, coords: Vector3i) -> i32 {
+        self.get_char_ranges(&char).reachable.cells.get(&coords)
+            .map_or(0, |cell| cell.fall_levels as i32 * self.state.fall_damage_per_level)
+    }
+
+    // Draw the path the focused character would take to the moused over cell
+    pub fn update_path_preview(&mut self) {
+        let mut path_cells: Vec<Vector3i> = Vec::new();
+        let mut path_end: Option<Vector3i> = None;
+        let mut cost: i32 = -1;
+
+        if let (Some(ranges), Some(mouse_coords)) = (&self.focused_ranges, self.last_mouse_coords) {
+            let reach_map: &ReachMap = &ranges.reachable;
+            let target: Vector3i = mouse_coords + Vector3i::UP; // Block above currently moused
+
+            if target != reach_map.origin && reach_map.can_stop_at(target) {
+                let path: Vec<Vector3i> = reach_map.get_path(target).expect("Cannot fail due to above check");
+
+                // Highlight block under every cell after the start
+                path_cells = path[1..path.len() - 1].iter().map(|cell| *cell + Vector3i::DOWN).collect();
+                path_end = Some(target + Vector3i::DOWN);
+
+                cost = reach_map.get_cost(target).expect("Cannot fail due to above check") as i32;
+            }
+        }
+
+        self.set_highlight_layer(HighlightLayer::Path, path_cells);
+        self.set_highlight_layer(HighlightLayer::PathEnd, path_end);
+
+        if cost != self.path_preview_cost {
+            self.path_preview_cost = cost;
+            self.base_mut().emit_signal("path_preview_changed".into(), &[Variant::from(cost)]);
+        }
+    }
+
+    // Movement cost of the previewed path, -1 if there isn't one
+    #[func]
+    pub fn get_path_preview_cost(&self) -> i32 {
+        self.path_preview_cost
     }
 
     #[func]
@@ -165,80 +624,775 @@ impl FieldGripMap {
         self.base().map_to_local(coords)
     }
 
-    // Change position of character currently on board
-    // Does not check whether char_ref at cur_pos is the same
-    // Overwrites whatever is at the position
-    // Current position needed to avoid binding issues
+    // Put a character where its unit now stands
+    // Takes the unit id rather than the character, as the character calling this is still bound
     #[func]
-    pub fn reposition_char_from_pos(&mut self, cur_pos: Vector3i, new_pos: Vector3i) {
-        let char_ref = self.char_refs.get(&cur_pos);
+    pub fn reposition_char(&mut self, unit_id: u64, new_pos: Vector3i) {
+        let Some(mut char_ref) = self.char_refs.get(&unit_id).cloned() else { return; };
+
+        // Moves and undos already put the unit there, this is for characters moved some other way, like the inspector
+        self.state.place_unit(unit_id, new_pos);
+
+        char_ref.set_position(self.get_world_pos_from_coords(new_pos));
+
+        self.invalidate_ranges();
+        self.base_mut().emit_signal("unit_moved".into(), &[char_ref.to_variant()]);
+    }
+
+    // Orders given to units, each goes through a command so it can be logged and undone
 
-        if let Some(char_ref) = char_ref {
-            let mut char_ref: Gd<FieldCharacter> = char_ref.clone();
+    // Send a character walking to coords along the cheapest path
+    // Returns false if it can't end its movement there
+    #[func]
+    pub fn move_char(&mut self, char: Gd<FieldCharacter>, coords: Vector3i) -> bool {
+        let unit: u64 = char.bind().get_unit_id();
+        let Some(unit_state) = self.state.get_unit(unit) else { return false; };
+        let (from, hp_before): (Vector3i, i32) = (unit_state.pos, unit_state.stats.hp);
+        let moved_before: bool = self.state.turn.has_moved(unit);
+
+        self.execute_command(BattleCommand::Move { unit, from, to: coords, moved_before, hp_before })
+    }
+
+    // Attack whoever is at target_coords, returns false if they can't be attacked
+    #[func]
+    pub fn attack_char(&mut self, attacker: Gd<FieldCharacter>, target_coords: Vector3i) -> bool {
+        let unit: u64 = attacker.bind().get_unit_id();
+        self.execute_command(BattleCommand::Attack { unit, target: target_coords })
+    }
+
+    // Heal whoever is at target_coords, returns false if they can't be healed
+    #[func]
+    pub fn heal_char(&mut self, healer: Gd<FieldCharacter>, target_coords: Vector3i) -> bool {
+        let unit: u64 = healer.bind().get_unit_id();
+        self.execute_command(BattleCommand::Heal { unit, target: target_coords })
+    }
+
+    // End a unit's phase without doing anything else
+    #[func]
+    pub fn wait_char(&mut self, char: Gd<FieldCharacter>) {
+        let unit: u64 = char.bind().get_unit_id();
+        self.execute_command(BattleCommand::Wait { unit });
+    }
+
+    // Run a command and log it, returns false if it couldn't be carried out
+    pub fn execute_command(&mut self, command: BattleCommand) -> bool {
+        let step: Option<ReplayStep> = self.get_replay_step(command);
+        let checksum: Option<u64> = self.get_replay_checksum();
+
+        if !self.run_command(command) { return false; }
+
+        self.action_log.record(command);
+        if let Some(step) = step {
+            self.record_replay_step(step, checksum);
+        }
+
+        true
+    }
 
-            char_ref.set_position(self.get_world_pos_from_coords(new_pos));
+    // Hand the turn to the next side before every unit is spent
+    #[func]
+    pub fn end_player_phase(&mut self) {
+        let checksum: Option<u64> = self.get_replay_checksum();
 
-            self.char_refs.remove_entry(&cur_pos);
-            self.char_refs.insert(new_pos, char_ref);
+        self.set_char_focused(None);
+        self.clear_char_ranges();
 
-            // TODO: rebuild trees?
+        // Deferred since the next phase may need the field straight away
+        if let Some(turn_manager) = &mut self.turn_manager {
+            turn_manager.call_deferred("end_phase".into(), &[]);
         }
+
+        self.record_replay_step(ReplayStep::EndPhase, checksum);
     }
 
-    pub fn show_range_tree(&mut self, node: &VecTree<Vector3i>, highlight_offset: i32) {
-        for child_node in node.children.iter() {
-            self.show_range_tree(child_node, highlight_offset);
+    fn run_command(&mut self, command: BattleCommand) -> bool {
+        let Some(char) = self.char_refs.get(&command.get_unit()).cloned() else { return false; };
+
+        match command {
+            BattleCommand::Move { to, .. } => self.apply_move(char, to),
+            BattleCommand::Attack { target, .. } => self.apply_attack(char, target),
+            BattleCommand::Heal { target, .. } => self.apply_heal(char, target),
+            BattleCommand::Wait { .. } => self.apply_wait(char),
         }
+    }
+
+    // Take back the last move, as long as nothing has happened since
+    #[func]
+    pub fn undo_command(&mut self) -> bool {
+        if self.is_any_char_moving() { return false; }
+        let checksum: Option<u64> = self.get_replay_checksum();
+        let Some(BattleCommand::Move { unit, from, moved_before, hp_before, .. }) = self.action_log.undo() else { return false; };
+        let Some(mut char) = self.char_refs.get(&unit).cloned() else { return false; };
+        if !self.state.undo_move(unit, from, hp_before, moved_before) { return false; }
+
+        self.set_char_focused(None);
+        self.clear_char_ranges();
 
-        // Highlight block under current pos
-        let pos: Vector3i = node.value + Vector3i::new(0, -1, 0);
-        self.set_overlay_block(pos, highlight_offset);
-        self.focus_highlighted_cells.push(pos);
+        // Set directly, going through the character would have it reposition itself while the field is busy
+        char.bind_mut().field_position = from;
+        char.bind_mut().hp = hp_before;
+        self.reposition_char(unit, from);
+
+        self.record_replay_step(ReplayStep::Undo, checksum);
+        true
     }
 
     #[func]
-    pub fn show_char_ranges(&mut self, char: Gd<FieldCharacter>) {
-        // TODO: Disable healable and attackable if range is 0
-        // TODO: Store these trees in field for movement data
-        let healable: VecTree<Vector3i> = char.bind().get_range_tree(&self, char.bind().movement_range + char.bind().heal_range);
-        self.show_range_tree(&healable, self.highlight_heal_offset);
+    pub fn redo_command(&mut self) -> bool {
+        if self.is_any_char_moving() { return false; }
+        let checksum: Option<u64> = self.get_replay_checksum();
+        let Some(command) = self.action_log.redo() else { return false; };
 
-        let attackable: VecTree<Vector3i> = char.bind().get_range_tree(&self, char.bind().movement_range + char.bind().attack_range);
-        self.show_range_tree(&attackable, self.highlight_attack_offset);
+        self.set_char_focused(None);
+        self.clear_char_ranges();
 
-        let reachable: VecTree<Vector3i> = char.bind().get_range_tree(&self, char.bind().movement_range);
-        self.show_range_tree(&reachable, self.highlight_move_offset);
+        if !self.run_command(command) { return false; }
 
-        // Keep mouse highlight on field
-        if let Some(mouse_coords) = self.last_mouse_coords {
-            self.last_highlight_cell_offset = self.highlight_move_offset; // Draw movement range under mouse when clicking
-            self.set_overlay_block(mouse_coords, self.highlight_offset);
+        self.action_log.redone(command);
+        self.record_replay_step(ReplayStep::Redo, checksum);
+        true
+    }
+
+    // Start a new log every phase, and keep the board for rewinding if this is the player's
+    // Connected to the turn manager's phase_started
+    #[func]
+    pub fn reset_action_log(&mut self, phase: CharType) {
+        self.action_log = ActionLog::new();
+        self.rewind_point = None;
+
+        if self.allow_rewind && phase == CharType::Player {
+            self.rewind_point = Some(self.get_save_data());
         }
     }
 
+    // Put everything back to how it was when the player's phase began, if the difficulty allows it
     #[func]
-    pub fn clear_char_ranges(&mut self) {
-        let last_range_cells: Vec<Vector3i> = self.focus_highlighted_cells.clone();
+    pub fn rewind_turn(&mut self) -> bool {
+        if !self.allow_rewind || self.is_any_char_moving() { return false; }
+        let Some(field_data) = self.rewind_point.clone() else { return false; };
+        let checksum: Option<u64> = self.get_replay_checksum();
+
+        // Same as loading a save, see SaveManager
+        self.load_save_data(&field_data);
+        if let Some(turn_manager) = &mut self.turn_manager {
+            turn_manager.bind_mut().resume_battle();
+        }
+        self.action_log = ActionLog::new();
+        self.record_replay_step(ReplayStep::Rewind, checksum);
 
-        for pos in last_range_cells.into_iter() {
-            let mut highlight_offset: i32 = 0;
+        true
+    }
 
-            // Don't clear if block is highlighted currently
-            if let Some(last_mouse_coords) = self.last_mouse_coords {
-                if pos == last_mouse_coords {
-                    self.last_highlight_cell_offset = highlight_offset;
-                    highlight_offset += self.highlight_offset;
-                }
+    // Replays find units by where they stood, see ReplayStep
+    fn get_replay_step(&self, command: BattleCommand) -> Option<ReplayStep> {
+        let pos: Vector3i = self.state.get_unit(command.get_unit())?.pos;
+
+        Some(match command {
+            BattleCommand::Move { from, to, .. } => ReplayStep::Move { from, to },
+            BattleCommand::Attack { target, .. } => ReplayStep::Attack { from: pos, target },
+            BattleCommand::Heal { target, .. } => ReplayStep::Heal { from: pos, target },
+            BattleCommand::Wait { .. } => ReplayStep::Wait { at: pos },
+        })
+    }
+
+    // Only worked out while there's a replay to record it in
+    fn get_replay_checksum(&self) -> Option<u64> {
+        self.replay_log.as_ref().map(|_| self.get_board_checksum())
+    }
+
+    fn record_replay_step(&mut self, step: ReplayStep, checksum: Option<u64>) {
+        if let (Some(replay_log), Some(checksum)) = (&mut self.replay_log, checksum) {
+            replay_log.record(step, checksum);
+        }
+    }
+
+    // Carry out a recorded step through the same calls the player and AI use
+    // Returns false if it can't be, which means the replay has desynced
+    pub fn apply_replay_step(&mut self, step: ReplayStep) -> bool {
+        match step {
+            ReplayStep::Move { from, to } => self.get_char_at(from).is_some_and(|char| self.move_char(char, to)),
+            ReplayStep::Attack { from, target } => self.get_char_at(from).is_some_and(|char| self.attack_char(char, target)),
+            ReplayStep::Heal { from, target } => self.get_char_at(from).is_some_and(|char| self.heal_char(char, target)),
+            ReplayStep::Wait { at } => {
+                let Some(char) = self.get_char_at(at) else { return false; };
+                self.wait_char(char);
+                true
             }
+            ReplayStep::Undo => self.undo_command(),
+            ReplayStep::Redo => self.redo_command(),
+            ReplayStep::Rewind => self.rewind_turn(),
+            ReplayStep::EndPhase => {
+                self.end_player_phase();
+                true
+            }
+        }
+    }
+
+    // Only take orders from a replay of this battle played from seed
+    // Returns false once anything has happened, as the board would no longer be where the replay starts
+    pub fn start_replay(&mut self, seed: u64) -> bool {
+        if !self.replay_log.as_ref().is_some_and(|replay_log| replay_log.steps.is_empty()) { return false; }
+
+        self.state.rng = BattleRng::new(seed);
+        self.replay_log = Some(ReplayLog::new(seed));
+        self.replaying = true;
+        self.set_char_focused(None);
+        self.clear_char_ranges();
+
+        true
+    }
+
+    // Let the player take over from wherever the replay got to
+    pub fn stop_replay(&mut self) {
+        self.replaying = false;
+    }
+
+    pub fn get_replay_log(&self) -> Option<&ReplayLog> {
+        self.replay_log.as_ref()
+    }
 
-            self.set_overlay_block(pos, highlight_offset);
+    // Replays can only start from a fresh battle, so one that was loaded can't be recorded
+    pub fn clear_replay_log(&mut self) {
+        self.replay_log = None;
+    }
+
+    // Checksum of the board for catching replays that have gone differently, see get_board_checksum
+    pub fn get_board_checksum(&self) -> u64 {
+        self.state.get_checksum()
+    }
+
+    // Character showing whichever unit stands at pos
+    fn get_char_at(&self, pos: Vector3i) -> Option<Gd<FieldCharacter>> {
+        self.state.get_unit_at(pos).and_then(|unit| self.char_refs.get(&unit.id)).cloned()
+    }
+
+    // The state moves the unit straight away, the character then walks there to catch up
+    // A fall that kills has already taken the unit off, the character is removed once it lands
+    fn apply_move(&mut self, mut char: Gd<FieldCharacter>, coords: Vector3i) -> bool {
+        let unit: u64 = char.bind().get_unit_id();
+        let Some(result) = self.state.move_unit(unit, coords) else { return false; };
+        let waypoints: Vec<Vector3> = self.get_path_waypoints(&result.path);
+
+        char.bind_mut().follow_path(waypoints, coords, result.fall_damage);
+
+        self.invalidate_ranges();
+        true
+    }
+
+    // Positions to walk through to follow a path, excluding the start
+    // Drops go over the edge before falling, climbs go straight up the slope or wall
+    pub fn get_path_waypoints(&self, path: &[Vector3i]) -> Vec<Vector3> {
+        let mut waypoints: Vec<Vector3> = Vec::with_capacity(path.len() * 2);
+
+        for step in path.windows(2) {
+            let (from, to): (Vector3i, Vector3i) = (step[0], step[1]);
+
+            if to.y < from.y {
+                waypoints.push(self.get_world_pos_from_coords(Vector3i::new(to.x, from.y, to.z)));
+            }
+
+            waypoints.push(self.get_world_pos_from_coords(to));
         }
 
-        self.focus_highlighted_cells.clear();
+        waypoints
+    }
+
+    // Units that already moved this phase can't move again
+    pub fn get_char_move_range(&self, char: &Gd<FieldCharacter>) -> u32 {
+        self.state.get_unit(char.bind().get_unit_id()).map_or(0, |unit| self.state.get_move_range(unit))
+    }
+
+    // Without a turn manager every unit can be controlled
+    pub fn can_control_char(&self, char: &Gd<FieldCharacter>) -> bool {
+        if self.turn_manager.is_none() { return true; }
+
+        self.is_player_phase() && self.state.turn.can_act(char.bind().get_unit_id())
+    }
+
+    // Nobody's phase once the battle is over, so the player can't give orders any more
+    // Read from the state rather than the turn manager, which may be asking the field something itself
+    fn is_player_phase(&self) -> bool {
+        let is_battle_over: bool = self.turn_manager.as_ref().is_some_and(|turn_manager| turn_manager.bind().is_battle_over());
+        self.state.turn.phase == CharType::Player && !is_battle_over
+    }
+
+    // Cells a character can attack from where it's standing
+    pub fn is_in_char_attack_map(&self, char: &Gd<FieldCharacter>, coords: Vector3i) -> bool {
+        self.get_char_ranges(char).attack_map.contains(&coords)
+    }
+
+    // Cells a character can heal from where it's standing
+    pub fn is_in_char_heal_map(&self, char: &Gd<FieldCharacter>, coords: Vector3i) -> bool {
+        self.get_char_ranges(char).heal_map.contains(coords)
+    }
+
+    fn apply_attack(&mut self, mut attacker: Gd<FieldCharacter>, target_coords: Vector3i) -> bool {
+        // Found before attacking, a defender that dies is gone from the state after
+        let Some(mut defender) = self.get_char_at(target_coords) else { return false; };
+        let Some(result) = self.state.attack_unit(attacker.bind().get_unit_id(), target_coords) else { return false; };
+
+        self.invalidate_ranges();
+
+        defender.bind_mut().hp = result.attack.defender_hp;
+        self.emit_unit_attacked(&attacker, &defender, &result.attack);
+
+        if let Some(counter) = result.counter {
+            attacker.bind_mut().hp = counter.defender_hp;
+            self.emit_unit_attacked(&defender, &attacker, &counter);
+        }
+
+        if result.attack.killed { self.remove_char(defender); }
+
+        if result.counter.is_some_and(|counter| counter.killed) {
+            self.remove_char(attacker);
+        } else {
+            self.finish_char_action(&attacker);
+        }
+
+        true
+    }
+
+    fn emit_unit_attacked(&mut self, attacker: &Gd<FieldCharacter>, defender: &Gd<FieldCharacter>, result: &AttackResult) {
+        self.base_mut().emit_signal("unit_attacked".into(), &[
+            attacker.to_variant(), defender.to_variant(), Variant::from(result.hit), Variant::from(result.crit), Variant::from(result.damage),
+        ]);
+    }
+
+    fn apply_heal(&mut self, healer: Gd<FieldCharacter>, target_coords: Vector3i) -> bool {
+        let Some(mut target) = self.get_char_at(target_coords) else { return false; };
+        let Some(result) = self.state.heal_unit(healer.bind().get_unit_id(), target_coords) else { return false; };
+
+        target.bind_mut().hp = result.target_hp;
+        self.invalidate_ranges();
+
+        self.base_mut().emit_signal("unit_healed".into(), &[
+            healer.to_variant(), target.to_variant(), Variant::from(result.amount),
+        ]);
+
+        self.finish_char_action(&healer);
+        true
+    }
+
+    // Swap the field's blocks for those of a GridMap scene, highlights and units are left alone
+    fn load_grid(&mut self, grid_scene: &Gd<PackedScene>) {
+        let grid: Gd<GridMap> = grid_scene.instantiate()
+            .and_then(|grid| grid.try_cast::<GridMap>().ok())
+            .expect("Level grid scene should have a GridMap root");
+
+        self.base_mut().clear();
+
+        let used_cells: Array<Vector3i> = grid.get_used_cells();
+        for cell in used_cells.iter_shared() {
+            let item: i32 = grid.get_cell_item(cell);
+            let orientation: i32 = grid.get_cell_item_orientation(cell);
+            self.base_mut().set_cell_item_ex(cell, item).orientation(orientation).done();
+        }
+
+        grid.free();
+    }
+
+    // Put a character that is already a child on the board, and its unit into the state
+    // Characters loaded from a save keep their id and home, anything new gets the next id and starts home
+    fn register_char(&mut self, mut char: Gd<FieldCharacter>) {
+        let field_pos: Vector3i = char.bind().get_field_position();
+
+        let unit_id: u64 = char.bind().get_unit_id();
+        if unit_id == 0 {
+            char.bind_mut().set_unit_id(self.next_unit_id);
+            char.bind_mut().home_position = field_pos;
+            self.next_unit_id += 1;
+        } else {
+            self.next_unit_id = self.next_unit_id.max(unit_id + 1);
+        }
+
+        char.set_position(self.get_world_pos_from_coords(field_pos));
+
+        let (unit, orders): (UnitState, AiOrders) = {
+            let char: GdRef<'_, FieldCharacter> = char.bind();
+            (char.get_unit_state(), char.get_ai_orders())
+        };
+        let unit_id: u64 = unit.id;
+
+        // Two characters on one cell, the later one stays off the board
+        if !self.state.add_unit(unit, Some(orders)) { return; }
+
+        self.char_refs.insert(unit_id, char);
+        self.invalidate_ranges();
+    }
+
+    // Add a unit from the level, None if its cell is already taken
+    fn spawn_char(&mut self, spawn: &Gd<UnitSpawn>, chartype: CharType) -> Option<Gd<FieldCharacter>> {
+        let spawn: GdRef<'_, UnitSpawn> = spawn.bind();
+        if self.state.get_unit_at(spawn.field_position).is_some() { return None; }
+
+        let unit_scene: Gd<PackedScene> = spawn.unit_scene.clone()
+            .or_else(|| self.level.as_ref().and_then(|level| level.bind().unit_scene.clone()))
+            .expect("Spawns need a unit scene, either their own or the level's");
+
+        let mut char: Gd<FieldCharacter> = unit_scene.instantiate()
+            .and_then(|char| char.try_cast::<FieldCharacter>().ok())
+            .expect("Unit scene should have a FieldCharacter root");
+
+        // Set directly rather than through setters, as the character isn't on the field yet
+        {
+            let mut char: GdMut<'_, FieldCharacter> = char.bind_mut();
+            char.chartype = chartype;
+            char.field_position = spawn.field_position;
+            char.definition = spawn.definition.clone();
+            char.overrides = spawn.overrides.clone();
+            char.unit_tag = spawn.unit_tag.clone();
+            char.ai_behaviour = spawn.ai_behaviour;
+            char.unit_group = spawn.unit_group;
+        }
+
+        // Readying the character applies its definition, so it has its real stats before registering
+        self.base_mut().add_child(char.clone().upcast::<Node>());
+        self.register_char(char.clone());
+
+        Some(char)
+    }
+
+    // Bring in the level's waves for turn, connected to the turn manager's turn_started
+    #[func]
+    pub fn spawn_reinforcements(&mut self, turn: i64) {
+        let Some(level) = self.level.clone() else { return; };
+        let waves: Vec<Gd<ReinforcementWave>> = level.bind().get_reinforcements(turn);
+
+        for wave in waves {
+            let wave: GdRef<'_, ReinforcementWave> = wave.bind();
+
+            for spawn in wave.spawns.iter_shared() {
+                self.spawn_char(&spawn, wave.chartype);
+            }
+        }
+    }
+
+    // Blocks, rng, turn and every unit with its spent flags
+    pub fn get_save_data(&self) -> Dictionary {
+        // Flattened like GridMap's own data, x, y, z, item and orientation for each cell
+        let mut cells: PackedInt32Array = PackedInt32Array::new();
+        let used_cells: Array<Vector3i> = self.base().get_used_cells();
+        for cell in used_cells.iter_shared() {
+            for value in [cell.x, cell.y, cell.z, self.get_cell_item(cell), self.base().get_cell_item_orientation(cell)] {
+                cells.push(value);
+            }
+        }
+
+        // Saved in id order so they're made again in the same order, and keep the AI's choices the same
+        let mut units: Array<Dictionary> = Array::new();
+        for unit_state in &self.state.units {
+            let Some(char_ref) = self.char_refs.get(&unit_state.id) else { continue; };
+
+            let mut unit: Dictionary = char_ref.bind().get_save_data();
+            unit.set("moved", self.state.turn.has_moved(unit_state.id));
+            unit.set("acted", self.state.turn.is_spent(unit_state.id));
+            units.push(unit);
+        }
+
+        let mut data: Dictionary = Dictionary::new();
+        data.set("rng_state", self.state.rng.state as i64);
+        data.set("turn", self.state.turn.turn as i64);
+        data.set("phase", self.state.turn.phase.to_variant());
+        data.set("next_unit_id", self.next_unit_id as i64); // Reinforcements get the same ids as if the battle had never been saved
+        data.set("cells", cells);
+        data.set("units", units);
+        data
+    }
+
+    // Units are all made again, saved ones may have died or not arrived yet
+    // The turn manager has to resume the battle after, see TurnManager::resume_battle
+    pub fn load_save_data(&mut self, data: &Dictionary) {
+        self.set_char_focused(None);
+        self.clear_char_ranges();
+        self.set_char_hovered(None);
+        self.hide_danger_zone();
+
+        self.state.rng.state = read_save_value(data, "rng_state", self.state.rng.state as i64) as u64;
+        self.next_unit_id = read_save_value(data, "next_unit_id", self.next_unit_id as i64) as u64;
+
+        let cells: PackedInt32Array = read_save_value(data, "cells", PackedInt32Array::new());
+        if !cells.is_empty() {
+            self.base_mut().clear();
+
+            for cell in cells.as_slice().chunks_exact(5) {
+                let coords: Vector3i = Vector3i::new(cell[0], cell[1], cell[2]);
+                self.base_mut().set_cell_item_ex(coords, cell[3]).orientation(cell[4]).done();
+            }
+
+            self.sync_grid();
+        }
+
+        let old_chars: Vec<Gd<FieldCharacter>> = self.char_refs.drain().map(|(_, char_ref)| char_ref).collect();
+        for mut char_ref in old_chars {
+            self.base_mut().remove_child(char_ref.clone().upcast::<Node>());
+            char_ref.queue_free();
+        }
+        self.range_caches.get_mut().clear();
+
+        self.state.clear_units();
+        self.state.turn = TurnState::new();
+        self.state.turn.turn = read_save_value(data, "turn", 1i64).max(1) as u32;
+        self.state.turn.phase = read_save_value(data, "phase", CharType::Player);
+
+        let units: Array<Dictionary> = read_save_value(data, "units", Array::new());
+        for unit in units.iter_shared() {
+            let Some(mut char) = FieldCharacter::from_save_data(&unit) else { continue; };
+
+            // Added before loading so its definition doesn't overwrite what was saved
+            self.base_mut().add_child(char.clone().upcast::<Node>());
+            char.bind_mut().load_save_data(&unit);
+            self.register_char(char.clone());
+
+            let id: u64 = char.bind().get_unit_id();
+            let moved: bool = read_save_value(&unit, "moved", false);
+            let acted: bool = read_save_value(&unit, "acted", false);
+            self.state.turn.set_flags(id, moved, acted);
+        }
+
+        self.invalidate_ranges();
+    }
+
+    // Take a character off the board for good
+    // Its unit may already be gone from the state, like after an attack or a deadly fall
+    #[func]
+    pub fn remove_char(&mut self, mut char: Gd<FieldCharacter>) {
+        let id: u64 = char.bind().get_unit_id();
+
+        if self.char_refs.get(&id) == Some(&char) {
+            self.state.remove_unit(id);
+            self.char_refs.remove(&id);
+            self.range_caches.get_mut().remove(&id);
+            self.invalidate_ranges();
+
+            // Deaths can't be taken back, so neither can anything before them
+            self.action_log = ActionLog::new();
+        }
+
+        if self.focused_char.as_ref() == Some(&char) {
+            self.set_char_focused(None);
+            self.clear_char_ranges();
+        }
+
+        if self.hovered_char.as_ref() == Some(&char) {
+            self.set_char_hovered(None);
+        }
+
+        self.base_mut().emit_signal("unit_died".into(), &[char.to_variant()]);
+        char.queue_free();
+        self.check_phase_done();
+    }
+
+    // Returns false if the unit isn't on the board any more
+    fn apply_wait(&mut self, char: Gd<FieldCharacter>) -> bool {
+        if !self.state.wait_unit(char.bind().get_unit_id()) { return false; }
+
+        self.finish_char_action(&char);
+        true
+    }
+
+    // Called once a unit is spent
+    fn finish_char_action(&mut self, char: &Gd<FieldCharacter>) {
+        if self.focused_char.as_ref() == Some(char) {
+            self.set_char_focused(None);
+            self.clear_char_ranges();
+        }
+
+        self.check_phase_done();
+    }
+
+    // Deferred, the turn manager reads the state back from the field
+    fn check_phase_done(&mut self) {
+        if let Some(turn_manager) = &mut self.turn_manager {
+            turn_manager.call_deferred("check_phase_done".into(), &[]);
+        }
+    }
+
+    pub fn get_battle_state(&self) -> &BattleState {
+        &self.state
+    }
+
+    // Go to the next phase, returns true if that started a new turn
+    // Called by the turn manager, which says so to everything else
+    pub fn advance_phase(&mut self) -> bool {
+        let new_turn: bool = self.state.end_phase();
+
+        self.invalidate_ranges();
+        new_turn
+    }
+
+    // Spend every unit of the phase, for when nothing is there to take them
+    pub fn skip_phase(&mut self) {
+        for id in self.state.turn.get_phase_units() {
+            self.state.wait_unit(id);
+        }
+    }
+
+    // Play the battle out count times from the board as it is now, with the AI on every side and seeds counting up from rng_seed
+    // Meant for balancing before the battle starts, reinforcements aren't included as they only exist once spawned
+    #[func]
+    pub fn simulate_battles(&self, count: i64, max_turns: i64) -> Dictionary {
+        let objectives: Objectives = self.level.as_ref().map_or_else(Objectives::default, |level| level.bind().get_objectives());
+        let (mut won, mut lost, mut undecided, mut total_turns): (i64, i64, i64, i64) = (0, 0, 0, 0);
+
+        for run in 0..count.max(0) {
+            let mut state: BattleState = self.state.clone();
+            state.rng = BattleRng::new((self.rng_seed + run) as u64);
+
+            let result: SimResult = state.simulate(&mut objectives.clone(), max_turns.max(1) as u32);
+            match result.outcome {
+                Some(BattleOutcome::Won(_)) => won += 1,
+                Some(BattleOutcome::Lost(_)) => lost += 1,
+                None => undecided += 1,
+            }
+            total_turns += result.turns as i64;
+        }
+
+        let mut dict: Dictionary = Dictionary::new();
+        dict.set("won", won);
+        dict.set("lost", lost);
+        dict.set("undecided", undecided); // Ran out of turns
+        dict.set("average_turns", if count > 0 { total_turns as f32 / count as f32 } else { 0.0 });
+        dict
+    }
+
+    #[func]
+    pub fn is_any_char_moving(&self) -> bool {
+        self.char_refs.values().any(|char_ref| char_ref.bind().is_moving())
+    }
+
+    // Type of every unit on the board by position
+    pub fn get_occupants(&self) -> Occupants {
+        self.state.get_occupants()
+    }
+
+    // Characters on one side, in id order so the AI's choices repeat
+    pub fn get_chars_of_type(&self, chartype: CharType) -> Vec<Gd<FieldCharacter>> {
+        self.state.units.iter()
+            .filter(|unit| unit.chartype == chartype)
+            .filter_map(|unit| self.char_refs.get(&unit.id).cloned())
+            .collect()
+    }
+
+    pub fn show_reach_map(&mut self, reach_map: &ReachMap, layer: HighlightLayer) {
+        // Nothing to highlight under cells that are only passed through
+        let cells: Vec<Vector3i> = reach_map.iter()
+            .filter(|(_, reach_cell)| reach_cell.can_stop)
+            .map(|(cell, _)| *cell)
+            .collect();
+
+        self.show_cells(cells, layer);
+    }
+
+    // Highlight block under each pos, replacing whatever the layer had before
+    pub fn show_cells(&mut self, cells: impl IntoIterator<Item = Vector3i>, layer: HighlightLayer) {
+        let blocks: Vec<Vector3i> = cells.into_iter().map(|cell| cell + Vector3i::DOWN).collect();
+        self.set_highlight_layer(layer, blocks);
+    }
+
+    #[func]
+    pub fn show_char_ranges(&mut self, char: Gd<FieldCharacter>) {
+        // TODO: Disable healable and attackable if range is 0
+        let ranges: Rc<UnitRanges> = self.get_char_ranges(&char);
+
+        self.show_reach_map(&ranges.heal_coverage, HighlightLayer::Heal);
+        // Every cell the weapon reaches from somewhere the char can stop, walkable or not
+        self.show_cells(ranges.attack_coverage.iter().copied(), HighlightLayer::Attack);
+        self.show_reach_map(&ranges.reachable, HighlightLayer::Move);
+
+        // Mark cells that can only be reached by falling too far
+        let fall_cells: Vec<Vector3i> = ranges.reachable.iter()
+            .filter(|(_, reach_cell)| reach_cell.can_stop && reach_cell.fall_levels > 0)
+            .map(|(cell, _)| *cell)
+            .collect();
+        self.show_cells(fall_cells, HighlightLayer::Fall);
+
+        // Kept for previewing paths while focused
+        self.focused_ranges = Some(ranges);
+        self.update_path_preview();
+    }
+
+    #[func]
+    pub fn clear_char_ranges(&mut self) {
+        self.focused_ranges = None;
+        self.update_path_preview();
+
+        // Hovered block and path keep their highlights
+        for layer in [HighlightLayer::Heal, HighlightLayer::Attack, HighlightLayer::Move, HighlightLayer::Fall] {
+            self.set_highlight_layer(layer, []);
+        }
     }
 
-    // TODO: Make this show/hide char information
     pub fn set_char_focused(&mut self, val: Option<Gd<FieldCharacter>>) {
-        self.focused_char = val;
+        if val == self.focused_char { return; }
+
+        self.focused_char = val.clone();
+
+        match val {
+            Some(char) => self.base_mut().emit_signal("unit_focused".into(), &[char.to_variant()]),
+            None => self.base_mut().emit_signal("unit_unfocused".into(), &[]),
+        };
+    }
+
+    // Let anything showing unit or terrain info know what the mouse is over now
+    fn update_hover(&mut self, mouse_coords: Vector3i) {
+        let hovered_char: Option<Gd<FieldCharacter>> = self.get_char_at(mouse_coords + Vector3i::UP);
+        self.set_char_hovered(hovered_char);
+
+        let terrain_info: Dictionary = self.get_terrain_info(mouse_coords);
+        self.base_mut().emit_signal("cell_hovered".into(), &[mouse_coords.to_variant(), terrain_info.to_variant()]);
     }
-}
\ No newline at end of file
+
+    fn set_char_hovered(&mut self, val: Option<Gd<FieldCharacter>>) {
+        if val == self.hovered_char { return; }
+
+        if self.hovered_char.take().is_some() {
+            self.base_mut().emit_signal("unit_unhovered".into(), &[]);
+        }
+
+        if let Some(char) = &val {
+            self.base_mut().emit_signal("unit_hovered".into(), &[char.to_variant()]);
+        }
+
+        self.hovered_char = val;
+    }
+
+    // Everything about a block a unit standing on it would care about
+    #[func]
+    pub fn get_terrain_info(&self, coords: Vector3i) -> Dictionary {
+        let mut costs: Dictionary = Dictionary::new();
+        for class in MovementClass::ALL {
+            costs.set(class.to_variant(), self.get_terrain_cost(coords, class));
+        }
+
+        let mut dict: Dictionary = Dictionary::new();
+        dict.set("block", self.base().get_cell_item(coords));
+        dict.set("height", coords.y + 1); // Level a unit standing on it would be at
+        dict.set("defence", self.get_terrain_defence(coords));
+        dict.set("is_ramp", self.get_ramp_dir(coords).is_some());
+        dict.set("costs", costs); // Class name -> cost, -1 if impassable
+
+        dict
+    }
+}
+
+impl FieldCells for FieldGripMap {
+    fn get_cell_item(&self, pos: Vector3i) -> i32 {
+        self.state.grid.get_cell_item(pos)
+    }
+
+    fn get_ramp_dir(&self, pos: Vector3i) -> Option<Vector3i> {
+        self.state.grid.get_ramp_dir(pos)
+    }
+
+    fn get_terrain_cost_for(&self, pos: Vector3i, class: MovementClass) -> Option<u32> {
+        self.state.grid.get_terrain_cost_for(pos, class)
+    }
+
+    fn get_terrain_defence(&self, pos: Vector3i) -> i32 {
+        self.state.grid.get_terrain_defence(pos)
+    }
+
+    fn is_in_bounds(&self, pos: Vector3i) -> bool {
+        self.state.grid.is_in_bounds(pos)
+    }
+}
@@ -4,10 +4,22 @@ mod inputpassnode;
 mod fieldgridmap;
 mod fieldcharacter;
 mod dithershaderrect;
+mod turnmanager;
+mod battleai;
+mod unitinfopanel;
+mod battleobjectives;
+mod savemanager;
+mod battlereplay;
 
 pub use gameroot::GameRoot;
 pub use panningcamera::PanningCamera;
 pub use inputpassnode::InputPassNode;
 pub use fieldgridmap::FieldGripMap;
 pub use fieldcharacter::FieldCharacter;
-pub use dithershaderrect::DitherShaderRect;
\ No newline at end of file
+pub use dithershaderrect::DitherShaderRect;
+pub use turnmanager::TurnManager;
+pub use battleai::BattleAi;
+pub use unitinfopanel::UnitInfoPanel;
+pub use battleobjectives::BattleObjectives;
+pub use savemanager::SaveManager;
+pub use battlereplay::BattleReplay;
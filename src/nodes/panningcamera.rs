@@ -1,4 +1,5 @@
 use crate::constants::*;
+use crate::types::read_save_value;
 
 use godot::{builtin::{math::ApproxEq, Basis, Dictionary, Plane, Quaternion, Rect2, Variant, Vector2, Vector3}, classes::{Camera3D, CanvasItem, ICamera3D, InputEvent, InputEventMouseButton, InputEventMouseMotion, PhysicsDirectSpaceState3D, PhysicsRayQueryParameters3D, PhysicsServer3D, ShaderMaterial}, global::{deg_to_rad, MouseButton}, meta::FromGodot, obj::{Base, Gd, WithBaseField}, prelude::{godot_api, GodotClass}};
 
@@ -213,6 +214,21 @@ impl PanningCamera {
         if self.zoom > self.get_zoom_max() { self.zoom = self.get_zoom_max(); }
     }
 
+    // Where the camera is looking from, for battle saves
+    pub fn get_save_data(&self) -> Dictionary {
+        let mut data: Dictionary = Dictionary::new();
+        data.set("centre_pos", self.centre_pos);
+        data.set("orbit_pos", self.orbit_pos);
+        data.set("zoom", self.zoom);
+        data
+    }
+
+    pub fn load_save_data(&mut self, data: &Dictionary) {
+        self.centre_pos = read_save_value(data, "centre_pos", self.centre_pos);
+        self.orbit_pos = read_save_value(data, "orbit_pos", self.orbit_pos);
+        self.set_zoom(read_save_value(data, "zoom", self.zoom));
+    }
+
     // Function that updates first intersection with the world from mouse position
     pub fn update_world_mouse_intersection(&mut self) {
         let pos: Vector2 = self.screen_last_pos;
@@ -0,0 +1,107 @@
+use crate::constants::*;
+use crate::nodes::{BattleObjectives, FieldGripMap, PanningCamera, TurnManager};
+use crate::types::read_save_value;
+
+use godot::{builtin::{Dictionary, GString, Variant}, classes::{file_access::ModeFlags, DirAccess, FileAccess, INode, InputEvent, Node}, meta::ToGodot, obj::{Base, Gd}, prelude::{godot_api, GodotClass}};
+
+// Suspends and resumes a battle through files in user://saves
+// Stored as plain variants, and read back with objects disallowed so a crafted save can't create any
+#[derive(GodotClass)]
+#[class(base=Node)]
+pub struct SaveManager {
+    base: Base<Node>,
+
+    #[export] field: Option<Gd<FieldGripMap>>,
+    #[export] turn_manager: Option<Gd<TurnManager>>,
+    #[export] objectives: Option<Gd<BattleObjectives>>,
+    #[export] cam: Option<Gd<PanningCamera>>,
+    #[export] quick_slot: GString, // Slot used by the quick save and load actions
+}
+
+#[godot_api]
+impl INode for SaveManager {
+    fn init(base: Base<Node>) -> Self {
+        Self {
+            base,
+
+            field: None,
+            turn_manager: None,
+            objectives: None,
+            cam: None,
+            quick_slot: GString::from("quick"),
+        }
+    }
+
+    fn unhandled_input(&mut self, event: Gd<InputEvent>) {
+        if event.is_action_pressed("QuickSaveAction".into()) {
+            self.save_battle(self.quick_slot.clone());
+        } else if event.is_action_pressed("QuickLoadAction".into()) {
+            self.load_battle(self.quick_slot.clone());
+        }
+    }
+}
+
+#[godot_api]
+impl SaveManager {
+    #[func]
+    pub fn get_save_path(slot: GString) -> GString {
+        GString::from(format!("{}/{}.battle", SAVE_DIR, slot))
+    }
+
+    // Returns false if the battle can't be saved right now or the file couldn't be written
+    #[func]
+    pub fn save_battle(&mut self, slot: GString) -> bool {
+        let Some(field) = &self.field else { return false; };
+
+        // Half finished moves can't be saved
+        if field.bind().is_any_char_moving() { return false; }
+
+        let mut data: Dictionary = Dictionary::new();
+        data.set("version", SAVE_VERSION);
+        data.set("field", field.bind().get_save_data());
+
+        if let Some(objectives) = &self.objectives {
+            data.set("objectives", objectives.bind().get_save_data());
+        }
+
+        if let Some(cam) = &self.cam {
+            data.set("cam", cam.bind().get_save_data());
+        }
+
+        DirAccess::make_dir_recursive_absolute(SAVE_DIR.into());
+
+        let Some(mut file) = FileAccess::open(Self::get_save_path(slot), ModeFlags::WRITE) else { return false; };
+        file.store_var(&data.to_variant());
+
+        true
+    }
+
+    // Returns false if there's no save in slot, or it's from a different version
+    #[func]
+    pub fn load_battle(&mut self, slot: GString) -> bool {
+        let (Some(mut field), Some(mut turn_manager)) = (self.field.clone(), self.turn_manager.clone()) else { return false; };
+
+        if field.bind().is_any_char_moving() { return false; }
+
+        let Some(file) = FileAccess::open(Self::get_save_path(slot), ModeFlags::READ) else { return false; };
+        let data: Variant = file.get_var();
+        let Ok(data) = data.try_to::<Dictionary>() else { return false; };
+
+        if read_save_value(&data, "version", 0i64) != SAVE_VERSION { return false; }
+
+        // Turn and phase come back with the field, the turn manager just picks the phase up again
+        field.bind_mut().load_save_data(&read_save_value(&data, "field", Dictionary::new()));
+        field.bind_mut().clear_replay_log();
+        turn_manager.bind_mut().resume_battle();
+
+        if let Some(objectives) = &mut self.objectives {
+            objectives.bind_mut().load_save_data(&read_save_value(&data, "objectives", Dictionary::new()));
+        }
+
+        if let Some(cam) = &mut self.cam {
+            cam.bind_mut().load_save_data(&read_save_value(&data, "cam", Dictionary::new()));
+        }
+
+        true
+    }
+}
@@ -0,0 +1,175 @@
+use crate::nodes::{BattleAi, FieldCharacter, FieldGripMap};
+use crate::types::{CharType, TurnState};
+
+use godot::{builtin::{GString, Variant}, classes::{INode, Node}, meta::ToGodot, obj::{Base, Gd, WithBaseField}, prelude::{godot_api, GodotClass}};
+
+// Runs the Player, Ally and Enemy phases of every turn in order
+// Turn, phase and who has acted live in the field's battle state, this only moves the battle along and says so
+#[derive(GodotClass)]
+#[class(base=Node)]
+pub struct TurnManager {
+    base: Base<Node>,
+    started: bool,
+    ended: bool,
+
+    #[export] pub ai: Option<Gd<BattleAi>>,
+    #[export] pub field: Option<Gd<FieldGripMap>>,
+}
+
+#[godot_api]
+impl INode for TurnManager {
+    fn init(base: Base<Node>) -> Self {
+        Self {
+            base,
+            started: false,
+            ended: false,
+
+            ai: None,
+            field: None,
+        }
+    }
+
+    // Deferred so the field has registered its units first
+    fn ready(&mut self) {
+        self.base_mut().call_deferred("start_battle".into(), &[]);
+    }
+}
+
+#[godot_api]
+impl TurnManager {
+    #[signal]
+    fn turn_started(turn: i64);
+
+    #[signal]
+    fn phase_started(phase: GString);
+
+    #[signal]
+    fn phase_ended(phase: GString);
+
+    #[func]
+    pub fn start_battle(&mut self) {
+        if self.started { return; }
+        self.started = true;
+
+        let turn: i64 = self.get_turn();
+        self.base_mut().emit_signal("turn_started".into(), &[Variant::from(turn)]);
+        self.begin_phase();
+    }
+
+    #[func]
+    pub fn get_turn(&self) -> i64 {
+        self.read_state(|state| state.turn as i64).unwrap_or(1)
+    }
+
+    #[func]
+    pub fn get_phase(&self) -> CharType {
+        self.read_state(|state| state.phase).unwrap_or(CharType::Player)
+    }
+
+    // Nobody's phase once the battle is over, so the player can't give orders any more
+    #[func]
+    pub fn is_player_phase(&self) -> bool {
+        !self.ended && self.get_phase() == CharType::Player
+    }
+
+    // Stop running phases, called once the battle is won or lost
+    #[func]
+    pub fn end_battle(&mut self) {
+        self.ended = true;
+    }
+
+    #[func]
+    pub fn is_battle_over(&self) -> bool {
+        self.ended
+    }
+
+    // Whether the player can give orders to this unit right now
+    #[func]
+    pub fn can_control(&self, unit: Gd<FieldCharacter>) -> bool {
+        let id: u64 = Self::get_unit_id(&unit);
+        self.is_player_phase() && self.read_state(|state| state.can_act(id)).unwrap_or(false)
+    }
+
+    #[func]
+    pub fn has_moved(&self, unit: Gd<FieldCharacter>) -> bool {
+        let id: u64 = Self::get_unit_id(&unit);
+        self.read_state(|state| state.has_moved(id)).unwrap_or(false)
+    }
+
+    #[func]
+    pub fn is_spent(&self, unit: Gd<FieldCharacter>) -> bool {
+        let id: u64 = Self::get_unit_id(&unit);
+        self.read_state(|state| state.is_spent(id)).unwrap_or(false)
+    }
+
+    #[func]
+    pub fn end_phase(&mut self) {
+        if self.ended { return; }
+        let Some(mut field) = self.field.clone() else { return; };
+
+        let phase: CharType = self.get_phase();
+        self.base_mut().emit_signal("phase_ended".into(), &[phase.to_variant()]);
+
+        if field.bind_mut().advance_phase() {
+            let turn: i64 = self.get_turn();
+            self.base_mut().emit_signal("turn_started".into(), &[Variant::from(turn)]);
+        }
+
+        self.begin_phase();
+    }
+
+    // Pick the phase up again after the field has loaded a save or rewound, nothing about it is kept here
+    pub fn resume_battle(&mut self) {
+        self.started = true;
+        self.ended = false;
+
+        self.base_mut().call_deferred("resume_phase".into(), &[]);
+    }
+
+    #[func]
+    fn resume_phase(&mut self) {
+        self.begin_phase();
+    }
+
+    fn begin_phase(&mut self) {
+        let phase: CharType = self.get_phase();
+        self.base_mut().emit_signal("phase_started".into(), &[phase.to_variant()]);
+
+        // Non-player phases go to the AI, without one their units just wait
+        if phase != CharType::Player {
+            match &mut self.ai {
+                Some(ai) => { ai.call_deferred("start_phase".into(), &[phase.to_variant()]); }
+                None => {
+                    if let Some(field) = &mut self.field {
+                        field.bind_mut().skip_phase();
+                    }
+                }
+            }
+        }
+
+        self.check_phase_done();
+    }
+
+    // Phases with nothing left to do end on their own
+    // Deferred so the phase doesn't change in the middle of whatever spent the last unit
+    // With no units on any side there is no phase to hand over to, so it stays put
+    // The field calls this deferred whenever a unit is spent or removed
+    #[func]
+    pub fn check_phase_done(&mut self) {
+        let is_done: bool = self.read_state(|state| state.has_units() && state.is_phase_done()).unwrap_or(false);
+
+        if self.started && !self.ended && is_done {
+            self.base_mut().call_deferred("end_phase".into(), &[]);
+        }
+    }
+
+    // Safe to bind the field here, as it only ever calls into this deferred or for the flags above
+    fn read_state<R>(&self, read: impl FnOnce(&TurnState) -> R) -> Option<R> {
+        let field: &Gd<FieldGripMap> = self.field.as_ref()?;
+        Some(read(&field.bind().get_battle_state().turn))
+    }
+
+    fn get_unit_id(unit: &Gd<FieldCharacter>) -> u64 {
+        unit.bind().get_unit_id()
+    }
+}
@@ -0,0 +1,157 @@
+use crate::nodes::{FieldCharacter, FieldGripMap};
+use crate::types::{CharType, WeaponRange};
+
+use godot::{builtin::{Callable, Dictionary, GString, Vector3i}, classes::{object::ConnectFlags, Control, IControl, RichTextLabel, Texture2D, TextureRect}, obj::{Base, Gd, GdRef, WithBaseField}, prelude::{godot_api, GodotClass}};
+
+// Shows the hovered unit, or the focused one when nothing is hovered, and the block under the mouse
+#[derive(GodotClass)]
+#[class(base=Control)]
+pub struct UnitInfoPanel {
+    base: Base<Control>,
+    focused_unit: Option<Gd<FieldCharacter>>,
+    hovered_unit: Option<Gd<FieldCharacter>>,
+    terrain_info: Option<Dictionary>,
+
+    #[export] field: Option<Gd<FieldGripMap>>,
+    #[export] label: Option<Gd<RichTextLabel>>,
+    #[export] portrait: Option<Gd<TextureRect>>, // Optional, shows the unit definition's portrait
+}
+
+#[godot_api]
+impl IControl for UnitInfoPanel {
+    fn init(base: Base<Control>) -> Self {
+        Self {
+            base,
+            focused_unit: None,
+            hovered_unit: None,
+            terrain_info: None,
+
+            field: None,
+            label: None,
+            portrait: None,
+        }
+    }
+
+    fn ready(&mut self) {
+        let Some(mut field) = self.field.clone() else { return; };
+        let panel: Gd<UnitInfoPanel> = self.to_gd();
+
+        // Deferred since the field is still busy when it sends these, and the panel reads from it
+        let signals: [(&str, &str); 5] = [
+            ("unit_hovered", "on_unit_hovered"),
+            ("unit_unhovered", "on_unit_unhovered"),
+            ("unit_focused", "on_unit_focused"),
+            ("unit_unfocused", "on_unit_unfocused"),
+            ("cell_hovered", "on_cell_hovered"),
+        ];
+
+        for (signal, method) in signals {
+            field.connect_ex(signal.into(), Callable::from_object_method(&panel, method))
+                .flags(ConnectFlags::DEFERRED.ord() as u32).done();
+        }
+
+        self.refresh();
+    }
+}
+
+#[godot_api]
+impl UnitInfoPanel {
+    #[func]
+    fn on_unit_hovered(&mut self, unit: Gd<FieldCharacter>) {
+        self.hovered_unit = Some(unit);
+        self.refresh();
+    }
+
+    #[func]
+    fn on_unit_unhovered(&mut self) {
+        self.hovered_unit = None;
+        self.refresh();
+    }
+
+    #[func]
+    fn on_unit_focused(&mut self, unit: Gd<FieldCharacter>) {
+        self.focused_unit = Some(unit);
+        self.refresh();
+    }
+
+    #[func]
+    fn on_unit_unfocused(&mut self) {
+        self.focused_unit = None;
+        self.refresh();
+    }
+
+    #[func]
+    fn on_cell_hovered(&mut self, _coords: Vector3i, terrain_info: Dictionary) {
+        self.terrain_info = Some(terrain_info);
+        self.refresh();
+    }
+
+    // Rewrite the label from whatever is currently hovered and focused
+    #[func]
+    pub fn refresh(&mut self) {
+        // Units can die between a signal being sent and it arriving
+        let unit: Option<Gd<FieldCharacter>> = self.hovered_unit.clone()
+            .or_else(|| self.focused_unit.clone())
+            .filter(|unit| unit.is_instance_valid());
+
+        let mut lines: Vec<String> = Vec::new();
+
+        if let Some(unit) = &unit {
+            lines.extend(self.get_unit_lines(unit));
+        }
+
+        if let Some(terrain_info) = &self.terrain_info {
+            lines.push(Self::get_terrain_line(terrain_info));
+        }
+
+        let visible: bool = !lines.is_empty();
+        self.base_mut().set_visible(visible);
+
+        if let Some(label) = &mut self.label {
+            label.set_text(GString::from(lines.join("\n")));
+        }
+
+        let texture: Option<Gd<Texture2D>> = unit.and_then(|unit| unit.bind().get_portrait());
+        if let Some(portrait) = &mut self.portrait {
+            portrait.set_visible(texture.is_some());
+            portrait.set_texture(texture);
+        }
+    }
+
+    fn get_unit_lines(&self, unit: &Gd<FieldCharacter>) -> Vec<String> {
+        let char: GdRef<'_, FieldCharacter> = unit.bind();
+        let name: String = char.get_display_name().to_string();
+
+        let side: &str = match char.chartype {
+            CharType::Player => "Player",
+            CharType::Ally => "Ally",
+            CharType::Enemy => "Enemy",
+        };
+
+        let weapon: WeaponRange = char.get_weapon_range();
+
+        // Bonus from the block the unit is standing on
+        let terrain_defence: i32 = match &self.field {
+            Some(field) => field.bind().get_terrain_defence_at(char.field_position),
+            None => 0,
+        };
+
+        vec![
+            format!("{} [{}]", name, side),
+            format!("HP {}/{}  ATK {}  DEF {}+{}", char.hp, char.max_hp, char.attack, char.defence, terrain_defence),
+            format!("ACC {}  EVA {}  CRT {}  HEAL {}", char.accuracy, char.evasion, char.critical, char.heal_power),
+            format!(
+                "MOVE {}  RANGE {}-{} {:?}  HEAL RANGE {}",
+                char.movement_range, weapon.min_range, weapon.max_range, weapon.shape, char.heal_range,
+            ),
+        ]
+    }
+
+    fn get_terrain_line(terrain_info: &Dictionary) -> String {
+        let height: i32 = terrain_info.get("height").and_then(|height| height.try_to::<i32>().ok()).unwrap_or(0);
+        let defence: i32 = terrain_info.get("defence").and_then(|defence| defence.try_to::<i32>().ok()).unwrap_or(0);
+        let is_ramp: bool = terrain_info.get("is_ramp").and_then(|is_ramp| is_ramp.try_to::<bool>().ok()).unwrap_or(false);
+
+        format!("TERRAIN  HEIGHT {}  DEF +{}{}", height, defence, if is_ramp { "  RAMP" } else { "" })
+    }
+}
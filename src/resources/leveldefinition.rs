@@ -0,0 +1,128 @@
+use crate::resources::{ObjectiveCondition, ReinforcementWave, UnitSpawn};
+use crate::types::{CharType, Condition, Objectives, VictoryCondition};
+
+use godot::{builtin::{Array, GString, PackedStringArray, Vector3i}, classes::{IResource, PackedScene, Resource}, obj::{Base, Gd}, prelude::{godot_api, GodotClass}};
+
+// Everything that makes one battle different from another, read by the field on ready
+#[derive(GodotClass)]
+#[class(base=Resource)]
+pub struct LevelDefinition {
+    base: Base<Resource>,
+
+    #[export] pub grid_scene: Option<Gd<PackedScene>>, // GridMap whose blocks replace the field's, None to keep the field's own
+    #[export] pub unit_scene: Option<Gd<PackedScene>>, // For spawns that don't set their own
+    #[export] pub player_spawns: Array<Gd<UnitSpawn>>,
+    #[export] pub ally_spawns: Array<Gd<UnitSpawn>>,
+    #[export] pub enemy_spawns: Array<Gd<UnitSpawn>>,
+    #[export] pub reinforcements: Array<Gd<ReinforcementWave>>,
+
+    #[export] pub victory_condition: VictoryCondition,
+    #[export] pub target_cells: Array<Vector3i>, // For Seize, Escort and Hold
+    #[export] pub survive_turns: i64, // For Survive
+    #[export] pub hold_turns: i64, // For Hold
+    #[export] pub escort_tag: GString, // For Escort, losing the unit loses the level
+    #[export] pub lord_tag: GString, // Level is lost if the unit with this tag dies, empty for none
+    #[export] pub protect_tags: PackedStringArray, // Level is lost if any of these units die
+    #[export] pub turn_limit: i64, // Level is lost once this turn ends without a win, 0 for none, ignored by Survive unless later than survive_turns
+    #[export] pub custom_victory: Option<Gd<ObjectiveCondition>>, // Used instead of victory_condition when set
+    #[export] pub custom_defeat: Option<Gd<ObjectiveCondition>>, // Used instead of the losing settings above when set
+}
+
+#[godot_api]
+impl IResource for LevelDefinition {
+    fn init(base: Base<Resource>) -> Self {
+        Self {
+            base,
+
+            grid_scene: None,
+            unit_scene: None,
+            player_spawns: Array::new(),
+            ally_spawns: Array::new(),
+            enemy_spawns: Array::new(),
+            reinforcements: Array::new(),
+
+            victory_condition: VictoryCondition::Rout,
+            target_cells: Array::new(),
+            survive_turns: 0,
+            hold_turns: 0,
+            escort_tag: GString::new(),
+            lord_tag: GString::new(),
+            protect_tags: PackedStringArray::new(),
+            turn_limit: 0,
+            custom_victory: None,
+            custom_defeat: None,
+        }
+    }
+}
+
+impl LevelDefinition {
+    // Units on one side at the start of the battle
+    pub fn get_spawns(&self, chartype: CharType) -> Array<Gd<UnitSpawn>> {
+        match chartype {
+            CharType::Player => self.player_spawns.clone(),
+            CharType::Ally => self.ally_spawns.clone(),
+            CharType::Enemy => self.enemy_spawns.clone(),
+        }
+    }
+
+    // Waves arriving at the start of turn
+    pub fn get_reinforcements(&self, turn: i64) -> Vec<Gd<ReinforcementWave>> {
+        self.reinforcements.iter_shared()
+            .filter(|wave| wave.bind().turn == turn)
+            .collect()
+    }
+
+    pub fn get_objectives(&self) -> Objectives {
+        Objectives {
+            victory: match &self.custom_victory {
+                Some(custom_victory) => custom_victory.bind().to_condition(),
+                None => self.get_victory(),
+            },
+            defeat: match &self.custom_defeat {
+                Some(custom_defeat) => custom_defeat.bind().to_condition(),
+                None => self.get_defeat(),
+            },
+        }
+    }
+
+    fn get_victory(&self) -> Condition {
+        let cells: Vec<Vector3i> = self.target_cells.iter_shared().collect();
+
+        match self.victory_condition {
+            VictoryCondition::Rout => Condition::routed(CharType::Enemy),
+            VictoryCondition::Seize => Condition::ReachCell { cells, tag: Self::get_tag(&self.lord_tag) },
+            VictoryCondition::Survive => Condition::TurnPassed(self.survive_turns.max(0) as u32),
+            VictoryCondition::Escort => Condition::ReachCell { cells, tag: Self::get_tag(&self.escort_tag) },
+            VictoryCondition::Hold => Condition::hold_cells(cells, self.hold_turns.max(0) as u32),
+        }
+    }
+
+    // Losing every player unit always loses, the rest only if they're set
+    fn get_defeat(&self) -> Condition {
+        let mut conditions: Vec<Condition> = vec![Condition::routed(CharType::Player)];
+
+        let mut lost_tags: Vec<GString> = vec![self.lord_tag.clone()];
+        if self.victory_condition == VictoryCondition::Escort {
+            lost_tags.push(self.escort_tag.clone());
+        }
+        lost_tags.extend(self.protect_tags.as_slice().iter().cloned());
+
+        for tag in lost_tags {
+            if let Some(tag) = Self::get_tag(&tag) {
+                conditions.push(Condition::unit_lost(tag));
+            }
+        }
+
+        // A limit no later than the turns to survive would make Survive unwinnable, so Survive wins out
+        let is_survive_limit: bool = self.victory_condition == VictoryCondition::Survive && self.turn_limit <= self.survive_turns;
+        if self.turn_limit > 0 && !is_survive_limit {
+            conditions.push(Condition::TurnPassed(self.turn_limit as u32));
+        }
+
+        Condition::Any(conditions)
+    }
+
+    fn get_tag(tag: &GString) -> Option<String> {
+        (!tag.is_empty()).then(|| tag.to_string())
+    }
+}
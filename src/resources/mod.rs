@@ -0,0 +1,11 @@
+mod unitdefinition;
+mod unitspawn;
+mod reinforcementwave;
+mod leveldefinition;
+mod objectivecondition;
+
+pub use unitdefinition::UnitDefinition;
+pub use unitspawn::UnitSpawn;
+pub use reinforcementwave::ReinforcementWave;
+pub use leveldefinition::LevelDefinition;
+pub use objectivecondition::ObjectiveCondition;
@@ -0,0 +1,59 @@
+use crate::types::{CharType, Condition, ConditionKind};
+
+use godot::{builtin::{Array, GString, Vector3i}, classes::{IResource, Resource}, obj::{Base, Gd}, prelude::{godot_api, GodotClass}};
+
+// A Condition declared in a level, kinds that don't use a setting ignore it
+#[derive(GodotClass)]
+#[class(base=Resource)]
+pub struct ObjectiveCondition {
+    base: Base<Resource>,
+
+    #[export] pub kind: ConditionKind,
+    #[export] pub children: Array<Gd<ObjectiveCondition>>, // For All and Any
+    #[export] pub side: CharType, // For Routed
+    #[export] pub unit_tag: GString, // For UnitLost and ReachCell, empty for any player unit when reaching
+    #[export] pub cells: Array<Vector3i>, // For ReachCell and HoldCells
+    #[export] pub turns: i64, // For HoldCells and TurnPassed
+}
+
+#[godot_api]
+impl IResource for ObjectiveCondition {
+    fn init(base: Base<Resource>) -> Self {
+        Self {
+            base,
+
+            kind: ConditionKind::Routed,
+            children: Array::new(),
+            side: CharType::Enemy,
+            unit_tag: GString::new(),
+            cells: Array::new(),
+            turns: 0,
+        }
+    }
+}
+
+impl ObjectiveCondition {
+    pub fn to_condition(&self) -> Condition {
+        let cells: Vec<Vector3i> = self.cells.iter_shared().collect();
+        let turns: u32 = self.turns.max(0) as u32;
+
+        match self.kind {
+            ConditionKind::All => Condition::All(self.get_child_conditions()),
+            ConditionKind::Any => Condition::Any(self.get_child_conditions()),
+            ConditionKind::Routed => Condition::routed(self.side),
+            ConditionKind::UnitLost => Condition::unit_lost(self.unit_tag.to_string()),
+            ConditionKind::ReachCell => Condition::ReachCell {
+                cells,
+                tag: (!self.unit_tag.is_empty()).then(|| self.unit_tag.to_string()),
+            },
+            ConditionKind::HoldCells => Condition::hold_cells(cells, turns),
+            ConditionKind::TurnPassed => Condition::TurnPassed(turns),
+        }
+    }
+
+    fn get_child_conditions(&self) -> Vec<Condition> {
+        self.children.iter_shared()
+            .map(|child| child.bind().to_condition())
+            .collect()
+    }
+}
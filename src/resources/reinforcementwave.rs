@@ -0,0 +1,29 @@
+use crate::resources::UnitSpawn;
+use crate::types::CharType;
+
+use godot::{builtin::Array, classes::{IResource, Resource}, obj::{Base, Gd}, prelude::{godot_api, GodotClass}};
+
+// Units that join one side at the start of a turn
+// Spawns whose cell is taken when the wave arrives never show up
+#[derive(GodotClass)]
+#[class(base=Resource)]
+pub struct ReinforcementWave {
+    base: Base<Resource>,
+
+    #[export] pub turn: i64,
+    #[export] pub chartype: CharType,
+    #[export] pub spawns: Array<Gd<UnitSpawn>>,
+}
+
+#[godot_api]
+impl IResource for ReinforcementWave {
+    fn init(base: Base<Resource>) -> Self {
+        Self {
+            base,
+
+            turn: 2,
+            chartype: CharType::Enemy,
+            spawns: Array::new(),
+        }
+    }
+}
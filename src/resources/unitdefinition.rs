@@ -0,0 +1,79 @@
+use crate::types::{MovementClass, WeaponShape};
+
+use godot::{builtin::GString, classes::{IResource, PackedScene, Resource, Texture2D}, obj::{Base, Gd}, prelude::{godot_api, GodotClass}};
+
+// Everything shared by every unit of one kind, saved as a .tres so balancing only touches one file
+// FieldCharacter copies these over itself when it enters the tree, then applies its own overrides
+#[derive(GodotClass)]
+#[class(base=Resource)]
+pub struct UnitDefinition {
+    base: Base<Resource>,
+
+    #[export] pub display_name: GString,
+    #[export] pub movement_class: MovementClass,
+    #[export] pub portrait: Option<Gd<Texture2D>>,
+    #[export] pub model_scene: Option<Gd<PackedScene>>, // Replaces the Model node in the unit's scene
+
+    // Base stats
+    #[export] pub max_hp: i32,
+    #[export] pub attack: i32,
+    #[export] pub defence: i32,
+    #[export] pub accuracy: i32,
+    #[export] pub evasion: i32,
+    #[export] pub critical: i32,
+    #[export] pub heal_power: i32,
+
+    // Chance out of 100 for each stat to go up on level up
+    #[export] pub max_hp_growth: i32,
+    #[export] pub attack_growth: i32,
+    #[export] pub defence_growth: i32,
+    #[export] pub accuracy_growth: i32,
+    #[export] pub evasion_growth: i32,
+    #[export] pub critical_growth: i32,
+    #[export] pub heal_power_growth: i32,
+
+    // Ranges
+    #[export] pub movement_range: u32,
+    #[export] pub attack_range: u32,
+    #[export] pub attack_min_range: u32,
+    #[export] pub attack_vertical_range: u32,
+    #[export] pub attack_shape: WeaponShape,
+    #[export] pub heal_range: u32,
+}
+
+#[godot_api]
+impl IResource for UnitDefinition {
+    fn init(base: Base<Resource>) -> Self {
+        Self {
+            base,
+
+            display_name: GString::new(),
+            movement_class: MovementClass::Infantry,
+            portrait: None,
+            model_scene: None,
+
+            max_hp: 10,
+            attack: 3,
+            defence: 0,
+            accuracy: 90,
+            evasion: 10,
+            critical: 0,
+            heal_power: 0,
+
+            max_hp_growth: 0,
+            attack_growth: 0,
+            defence_growth: 0,
+            accuracy_growth: 0,
+            evasion_growth: 0,
+            critical_growth: 0,
+            heal_power_growth: 0,
+
+            movement_range: 1,
+            attack_range: 1,
+            attack_min_range: 1,
+            attack_vertical_range: 1,
+            attack_shape: WeaponShape::Diamond,
+            heal_range: 0,
+        }
+    }
+}
@@ -0,0 +1,36 @@
+use crate::resources::UnitDefinition;
+use crate::types::AiBehaviour;
+
+use godot::{builtin::{Dictionary, GString, Vector3i}, classes::{IResource, PackedScene, Resource}, obj::{Base, Gd}, prelude::{godot_api, GodotClass}};
+
+// One unit a level places on the field, its side comes from the list it's in
+#[derive(GodotClass)]
+#[class(base=Resource)]
+pub struct UnitSpawn {
+    base: Base<Resource>,
+
+    #[export] pub unit_scene: Option<Gd<PackedScene>>, // None to use the level's
+    #[export] pub definition: Option<Gd<UnitDefinition>>,
+    #[export] pub overrides: Dictionary, // Passed on to the unit, see FieldCharacter
+    #[export] pub field_position: Vector3i,
+    #[export] pub unit_tag: GString, // Lets objectives refer to this unit
+    #[export] pub ai_behaviour: AiBehaviour,
+    #[export] pub unit_group: i32,
+}
+
+#[godot_api]
+impl IResource for UnitSpawn {
+    fn init(base: Base<Resource>) -> Self {
+        Self {
+            base,
+
+            unit_scene: None,
+            definition: None,
+            overrides: Dictionary::new(),
+            field_position: Vector3i::ZERO,
+            unit_tag: GString::new(),
+            ai_behaviour: AiBehaviour::Aggressive,
+            unit_group: 0,
+        }
+    }
+}
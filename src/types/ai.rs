@@ -0,0 +1,277 @@
+use crate::types::{forecast_exchange, get_strike_context, resolve_heal, ExchangeForecast, FieldCells, HealResult, ReachMap, StrikeContext, UnitRanges, UnitState};
+
+use std::{collections::{HashMap, HashSet}, rc::Rc};
+use godot::{builtin::{GString, Vector3i}, prelude::{Export, GodotConvert, Var}};
+
+#[derive(GodotConvert, Var, Export, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[godot(via = GString)]
+pub enum AiBehaviour {
+    Aggressive,   // Chase and attack anything
+    Defensive,    // Prefer safe attacks and good terrain
+    HoldPosition, // Never move, attack whatever comes in range
+    GuardArea,    // Stay within guard radius of home
+}
+
+// How much each part of an option counts towards its score
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct AiWeights {
+    pub damage_dealt: f32,
+    pub damage_taken: f32,
+    pub kill: f32,
+    pub terrain: f32,
+    pub distance: f32, // Per cell away from the goal
+    pub healing: f32,  // Per point of hp restored
+}
+
+impl AiBehaviour {
+    pub fn get_weights(&self) -> AiWeights {
+        match self {
+            AiBehaviour::Aggressive => AiWeights { damage_dealt: 1.0, damage_taken: 0.5, kill: 10.0, terrain: 0.5, distance: 0.5, healing: 0.5 },
+            AiBehaviour::Defensive => AiWeights { damage_dealt: 0.8, damage_taken: 1.5, kill: 8.0, terrain: 1.5, distance: 0.1, healing: 1.5 },
+            AiBehaviour::HoldPosition => AiWeights { damage_dealt: 1.0, damage_taken: 1.0, kill: 10.0, terrain: 0.0, distance: 0.0, healing: 1.0 },
+            AiBehaviour::GuardArea => AiWeights { damage_dealt: 1.0, damage_taken: 1.0, kill: 10.0, terrain: 1.0, distance: 0.3, healing: 1.0 },
+        }
+    }
+}
+
+// What a single unit has been told to do
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct AiOrders {
+    pub behaviour: AiBehaviour,
+    pub home: Vector3i, // Centre of the guarded area
+    pub guard_radius: u32,
+    pub objective: Option<Vector3i>, // Headed for when nothing can be attacked, nearest hostile if None
+}
+
+// What to do once a unit has moved, aimed at whoever stands in the cell
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum AiAction {
+    Attack(Vector3i),
+    Heal(Vector3i),
+}
+
+// Where to move and what to do once there, None to just wait
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct AiPlan {
+    pub dest: Vector3i,
+    pub action: Option<AiAction>,
+    pub score: f32,
+}
+
+// Score every reachable cell and every attack or heal from it, and pick the best
+// units should include every unit on the field, unit itself included, with ranges for each by id
+pub fn plan_turn<F: FieldCells>(
+    field: &F, unit: &UnitState, units: &[UnitState], orders: &AiOrders, ranges: &HashMap<u64, Rc<UnitRanges>>,
+) -> AiPlan {
+    let weights: AiWeights = orders.behaviour.get_weights();
+    let reachable: &ReachMap = &ranges.get(&unit.id).expect("Every unit should have its ranges").reachable;
+
+    let hostiles: Vec<&UnitState> = units.iter()
+        .filter(|other| other.chartype.is_hostile_to(unit.chartype))
+        .collect();
+
+    // Only worth going out of the way for anyone missing hp
+    let can_heal: bool = unit.heal_range > 0 && unit.stats.heal_power > 0;
+    let hurt_allies: Vec<&UnitState> = units.iter()
+        .filter(|other| other.id != unit.id && !other.chartype.is_hostile_to(unit.chartype) && other.stats.hp < other.stats.max_hp)
+        .collect();
+
+    // Where each hostile can strike back doesn't depend on where this unit goes
+    let counter_maps: HashMap<u64, &HashSet<Vector3i>> = hostiles.iter()
+        .filter(|hostile| hostile.weapon.max_range > 0)
+        .map(|hostile| (hostile.id, &ranges.get(&hostile.id).expect("Every unit should have its ranges").attack_map))
+        .collect();
+
+    let goal: Option<Vector3i> = match orders.behaviour {
+        AiBehaviour::GuardArea => Some(orders.home),
+        _ => orders.objective.or_else(|| {
+            hostiles.iter().map(|hostile| hostile.pos).min_by_key(|pos| (get_distance(unit.pos, *pos), pos.x, pos.y, pos.z))
+        }),
+    };
+
+    // Sorted since map order isn't stable, and the same board should always give the same plan
+    let mut dests: Vec<Vector3i> = reachable.iter()
+        .filter(|(_, cell)| cell.can_stop)
+        .map(|(pos, _)| *pos)
+        .filter(|pos| is_allowed_dest(unit, orders, *pos))
+        .collect();
+    dests.sort_by_key(|pos| (pos.x, pos.y, pos.z));
+
+    let mut best: AiPlan = AiPlan { dest: unit.pos, action: None, score: f32::MIN };
+
+    for dest in dests {
+        let position_score: f32 = score_position(field, &weights, dest, goal);
+        if position_score > best.score {
+            best = AiPlan { dest, action: None, score: position_score };
+        }
+
+        if can_heal && !hurt_allies.is_empty() {
+            let heal_map: ReachMap = unit.get_heal_map_from(field, dest);
+
+            for ally in hurt_allies.iter() {
+                if !heal_map.contains(ally.pos) { continue; }
+
+                let heal: HealResult = resolve_heal(&unit.stats, &ally.stats);
+                let score: f32 = position_score + heal.amount as f32 * weights.healing;
+
+                if score > best.score {
+                    best = AiPlan { dest, action: Some(AiAction::Heal(ally.pos)), score };
+                }
+            }
+        }
+
+        if unit.weapon.max_range == 0 { continue; }
+
+        let attack_map: HashSet<Vector3i> = unit.get_attack_map_from(field, dest);
+
+        for hostile in hostiles.iter() {
+            if !attack_map.contains(&hostile.pos) { continue; }
+
+            let context: StrikeContext = get_strike_context(field, dest, unit.profile.height, hostile.pos);
+            let counter_context: Option<StrikeContext> = counter_maps.get(&hostile.id)
+                .filter(|counter_map| counter_map.contains(&dest))
+                .map(|_| get_strike_context(field, hostile.pos, hostile.profile.height, dest));
+
+            let forecast: ExchangeForecast = forecast_exchange(&unit.stats, &hostile.stats, &context, counter_context.as_ref());
+            let score: f32 = position_score + score_attack(&weights, &forecast);
+
+            if score > best.score {
+                best = AiPlan { dest, action: Some(AiAction::Attack(hostile.pos)), score };
+            }
+        }
+    }
+
+    best
+}
+
+pub fn score_attack(weights: &AiWeights, forecast: &ExchangeForecast) -> f32 {
+    let kill_chance: f32 = if forecast.defender_hp == 0 { forecast.attack.hit_chance as f32 / 100.0 } else { 0.0 };
+    let damage_dealt: f32 = forecast.attack.get_expected_damage();
+
+    // Dead defenders don't counter
+    let damage_taken: f32 = forecast.counter.map_or(0.0, |counter| counter.get_expected_damage()) * (1.0 - kill_chance);
+
+    damage_dealt * weights.damage_dealt + kill_chance * weights.kill - damage_taken * weights.damage_taken
+}
+
+pub fn score_position<F: FieldCells>(field: &F, weights: &AiWeights, dest: Vector3i, goal: Option<Vector3i>) -> f32 {
+    let terrain: f32 = field.get_terrain_defence(dest + Vector3i::DOWN) as f32 * weights.terrain;
+    let distance: f32 = goal.map_or(0.0, |goal| get_distance(dest, goal) as f32 * weights.distance);
+
+    terrain - distance
+}
+
+fn is_allowed_dest(unit: &UnitState, orders: &AiOrders, dest: Vector3i) -> bool {
+    match orders.behaviour {
+        AiBehaviour::HoldPosition => dest == unit.pos,
+        AiBehaviour::GuardArea => get_distance(dest, orders.home) <= orders.guard_radius as i32,
+        _ => true,
+    }
+}
+
+// Distance across the field, ignoring height
+pub fn get_distance(a: Vector3i, b: Vector3i) -> i32 {
+    (a.x - b.x).abs() + (a.z - b.z).abs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::testing::{cell, grid, unit};
+    use crate::types::{BattleGrid, CharType, Occupants};
+
+    fn plan(field: &BattleGrid, unit: &UnitState, units: &[UnitState], behaviour: AiBehaviour) -> AiPlan {
+        let occupants: Occupants = units.iter().map(|unit| (unit.pos, unit.chartype)).collect();
+        let ranges: HashMap<u64, Rc<UnitRanges>> = units.iter()
+            .map(|unit| (unit.id, Rc::new(UnitRanges::build(field, unit, unit.movement_range, &occupants))))
+            .collect();
+        let orders: AiOrders = AiOrders { behaviour, home: unit.pos, guard_radius: 2, objective: None };
+
+        plan_turn(field, unit, units, &orders, &ranges)
+    }
+
+    #[test]
+    fn aggressive_units_close_in_on_far_hostiles() {
+        let enemy: UnitState = unit(1, cell(0, 0), CharType::Enemy, 10);
+        let player: UnitState = unit(2, cell(8, 0), CharType::Player, 10);
+
+        let plan: AiPlan = plan(&grid(10), &enemy, &[enemy.clone(), player], AiBehaviour::Aggressive);
+
+        assert_eq!(plan.dest, cell(3, 0));
+        assert_eq!(plan.action, None);
+    }
+
+    #[test]
+    fn attacks_go_to_the_hostile_that_can_be_killed() {
+        let enemy: UnitState = unit(1, cell(2, 2), CharType::Enemy, 10);
+        let healthy: UnitState = unit(2, cell(2, 4), CharType::Player, 10);
+        let weak: UnitState = unit(3, cell(4, 2), CharType::Player, 4);
+
+        let plan: AiPlan = plan(&grid(10), &enemy, &[enemy.clone(), healthy, weak.clone()], AiBehaviour::Aggressive);
+
+        assert_eq!(plan.action, Some(AiAction::Attack(weak.pos)));
+        assert_eq!(get_distance(plan.dest, weak.pos), 1);
+    }
+
+    #[test]
+    fn healers_go_to_hurt_allies() {
+        let mut healer: UnitState = unit(1, cell(0, 0), CharType::Enemy, 10);
+        healer.weapon.max_range = 0;
+        healer.heal_range = 1;
+        healer.stats.heal_power = 5;
+        let hurt: UnitState = unit(2, cell(0, 3), CharType::Enemy, 3);
+        let healthy: UnitState = unit(3, cell(3, 0), CharType::Enemy, 10);
+
+        let plan: AiPlan = plan(&grid(10), &healer, &[healer.clone(), hurt.clone(), healthy], AiBehaviour::Defensive);
+
+        assert_eq!(plan.action, Some(AiAction::Heal(hurt.pos)));
+        assert!(get_distance(plan.dest, hurt.pos) <= 1);
+    }
+
+    #[test]
+    fn healers_leave_allies_on_full_hp_alone() {
+        let mut healer: UnitState = unit(1, cell(0, 0), CharType::Enemy, 10);
+        healer.weapon.max_range = 0;
+        healer.heal_range = 1;
+        healer.stats.heal_power = 5;
+        let healthy: UnitState = unit(2, cell(0, 2), CharType::Enemy, 10);
+
+        let plan: AiPlan = plan(&grid(10), &healer, &[healer.clone(), healthy], AiBehaviour::Defensive);
+
+        assert_eq!(plan.action, None);
+    }
+
+    #[test]
+    fn held_units_never_move() {
+        let enemy: UnitState = unit(1, cell(0, 0), CharType::Enemy, 10);
+        let player: UnitState = unit(2, cell(2, 0), CharType::Player, 1);
+
+        let plan: AiPlan = plan(&grid(10), &enemy, &[enemy.clone(), player], AiBehaviour::HoldPosition);
+
+        assert_eq!(plan.dest, enemy.pos);
+        assert_eq!(plan.action, None);
+    }
+
+    #[test]
+    fn held_units_attack_whatever_is_in_range() {
+        let enemy: UnitState = unit(1, cell(0, 0), CharType::Enemy, 10);
+        let player: UnitState = unit(2, cell(1, 0), CharType::Player, 4);
+
+        let plan: AiPlan = plan(&grid(10), &enemy, &[enemy.clone(), player.clone()], AiBehaviour::HoldPosition);
+
+        assert_eq!(plan.dest, enemy.pos);
+        assert_eq!(plan.action, Some(AiAction::Attack(player.pos)));
+    }
+
+    #[test]
+    fn defensive_units_prefer_good_terrain() {
+        let mut field: BattleGrid = grid(10);
+        field.set_cell(Vector3i::new(1, 0, 1), 1, 0);
+        field.terrain_defence.insert(1, 3);
+        let enemy: UnitState = unit(1, cell(0, 0), CharType::Enemy, 10);
+
+        let plan: AiPlan = plan(&field, &enemy, &[enemy.clone()], AiBehaviour::Defensive);
+
+        assert_eq!(plan.dest, cell(1, 1));
+    }
+}
@@ -0,0 +1,125 @@
+use godot::builtin::Vector3i;
+
+// One order given to a unit, anything that changes the board is one of these
+// Units are referred to by id so commands outlive the nodes they were given to
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BattleCommand {
+    Move { unit: u64, from: Vector3i, to: Vector3i, moved_before: bool, hp_before: i32 }, // Flag and hp to put back on undo, falls can hurt
+    Attack { unit: u64, target: Vector3i },
+    Heal { unit: u64, target: Vector3i },
+    Wait { unit: u64 },
+}
+
+impl BattleCommand {
+    pub fn get_unit(&self) -> u64 {
+        match self {
+            BattleCommand::Move { unit, .. }
+            | BattleCommand::Attack { unit, .. }
+            | BattleCommand::Heal { unit, .. }
+            | BattleCommand::Wait { unit } => *unit,
+        }
+    }
+
+    // Only moves can be taken back, everything else rolls dice or ends the unit's phase
+    pub fn is_undoable(&self) -> bool {
+        matches!(self, BattleCommand::Move { .. })
+    }
+}
+
+// Commands given this phase, newest last, and those undone since the last new one
+#[derive(Clone, Debug, Default)]
+pub struct ActionLog {
+    done: Vec<BattleCommand>,
+    undone: Vec<BattleCommand>,
+}
+
+impl ActionLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // A new command means whatever was undone can't be redone any more
+    pub fn record(&mut self, command: BattleCommand) {
+        self.done.push(command);
+        self.undone.clear();
+    }
+
+    // Newest command if nothing irreversible has happened since, the caller has to reverse it
+    pub fn undo(&mut self) -> Option<BattleCommand> {
+        if !self.done.last()?.is_undoable() { return None; }
+
+        let command: BattleCommand = self.done.pop().expect("Cannot fail due to above check");
+        self.undone.push(command);
+        Some(command)
+    }
+
+    // Last undone command, the caller has to run it again and pass it to redone
+    pub fn redo(&mut self) -> Option<BattleCommand> {
+        self.undone.pop()
+    }
+
+    // Log a command from redo without losing the rest that can be redone
+    pub fn redone(&mut self, command: BattleCommand) {
+        self.done.push(command);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn step(unit: u64, x: i32) -> BattleCommand {
+        BattleCommand::Move { unit, from: Vector3i::ZERO, to: Vector3i::new(x, 0, 0), moved_before: false, hp_before: 10 }
+    }
+
+    #[test]
+    fn moves_undo_newest_first() {
+        let mut log: ActionLog = ActionLog::new();
+        log.record(step(1, 1));
+        log.record(step(2, 2));
+
+        assert_eq!(log.undo(), Some(step(2, 2)));
+        assert_eq!(log.undo(), Some(step(1, 1)));
+        assert_eq!(log.undo(), None);
+    }
+
+    #[test]
+    fn only_moves_can_be_undone() {
+        let mut log: ActionLog = ActionLog::new();
+        log.record(step(1, 1));
+        log.record(BattleCommand::Attack { unit: 1, target: Vector3i::new(2, 0, 0) });
+
+        assert_eq!(log.undo(), None);
+
+        let mut log: ActionLog = ActionLog::new();
+        log.record(step(1, 1));
+        log.record(BattleCommand::Wait { unit: 1 });
+
+        assert_eq!(log.undo(), None);
+    }
+
+    #[test]
+    fn recording_clears_redo() {
+        let mut log: ActionLog = ActionLog::new();
+        log.record(step(1, 1));
+        log.undo();
+        log.record(step(2, 2));
+
+        assert_eq!(log.redo(), None);
+    }
+
+    #[test]
+    fn redone_commands_keep_the_rest_to_redo() {
+        let mut log: ActionLog = ActionLog::new();
+        log.record(step(1, 1));
+        log.record(step(2, 2));
+        log.undo();
+        log.undo();
+
+        let command: BattleCommand = log.redo().expect("Two moves were undone");
+        log.redone(command);
+
+        assert_eq!(command, step(1, 1));
+        assert_eq!(log.redo(), Some(step(2, 2)));
+    }
+}
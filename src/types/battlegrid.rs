@@ -0,0 +1,110 @@
+use crate::types::{rotate_by_orientation, FieldCells, MovementClass, TerrainCosts};
+
+use std::collections::HashMap;
+use godot::{builtin::Vector3i, classes::GridMap};
+
+// Blocks of a field and the tables describing them, kept apart from any GridMap
+// The field copies its blocks in here, and anything headless can build one by hand
+#[derive(Clone, Debug)]
+pub struct BattleGrid {
+    pub cells: HashMap<Vector3i, (i32, i32)>, // Item and orientation of every used cell
+    pub bounds: Option<(Vector3i, Vector3i)>,  // Min and max used cells
+    pub slope_index: i32,
+    pub stair_index: i32,        // -1 if the field has no stairs
+    pub ramp_ascend_dir: Vector3i, // Low to high edge of slopes and stairs with no rotation
+    pub terrain_costs: TerrainCosts,
+    pub class_costs: HashMap<MovementClass, TerrainCosts>,
+    pub terrain_defence: HashMap<i32, i32>, // Base item -> defence bonus for units standing on it
+}
+
+impl BattleGrid {
+    pub fn new() -> Self {
+        Self {
+            cells: HashMap::new(),
+            bounds: None,
+            slope_index: 0,
+            stair_index: -1,
+            ramp_ascend_dir: Vector3i::LEFT,
+            terrain_costs: TerrainCosts::new(),
+            class_costs: HashMap::new(),
+            terrain_defence: HashMap::new(),
+        }
+    }
+
+    // Place a block, INVALID_CELL_ITEM clears the cell
+    // Bounds only grow here, call update_bounds after clearing cells at the edge
+    pub fn set_cell(&mut self, pos: Vector3i, item: i32, orientation: i32) {
+        if item == GridMap::INVALID_CELL_ITEM {
+            self.cells.remove(&pos);
+            return;
+        }
+
+        self.cells.insert(pos, (item, orientation));
+        self.bounds = Some(match self.bounds {
+            Some((min, max)) => (min.coord_min(pos), max.coord_max(pos)),
+            None => (pos, pos),
+        });
+    }
+
+    pub fn clear(&mut self) {
+        self.cells.clear();
+        self.bounds = None;
+    }
+
+    pub fn update_bounds(&mut self) {
+        self.bounds = None;
+
+        for pos in self.cells.keys() {
+            self.bounds = Some(match self.bounds {
+                Some((min, max)) => (min.coord_min(*pos), max.coord_max(*pos)),
+                None => (*pos, *pos),
+            });
+        }
+    }
+
+    pub fn get_orientation(&self, pos: Vector3i) -> i32 {
+        self.cells.get(&pos).map_or(GridMap::INVALID_CELL_ITEM, |(_, orientation)| *orientation)
+    }
+}
+
+impl Default for BattleGrid {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FieldCells for BattleGrid {
+    fn get_cell_item(&self, pos: Vector3i) -> i32 {
+        self.cells.get(&pos).map_or(GridMap::INVALID_CELL_ITEM, |(item, _)| *item)
+    }
+
+    fn get_ramp_dir(&self, pos: Vector3i) -> Option<Vector3i> {
+        let (item, orientation): (i32, i32) = *self.cells.get(&pos)?;
+        if item != self.slope_index && item != self.stair_index { return None; }
+
+        rotate_by_orientation(orientation, self.ramp_ascend_dir)
+    }
+
+    // Class overrides take priority over the field's table
+    fn get_terrain_cost_for(&self, pos: Vector3i, class: MovementClass) -> Option<u32> {
+        let base_item: i32 = self.get_cell_item(pos);
+
+        if let Some(cost) = self.class_costs.get(&class).and_then(|table| table.costs.get(&base_item)) {
+            return *cost;
+        }
+
+        self.terrain_costs.get_cost(base_item)
+    }
+
+    fn get_terrain_defence(&self, pos: Vector3i) -> i32 {
+        self.terrain_defence.get(&self.get_cell_item(pos)).copied().unwrap_or(0)
+    }
+
+    fn is_in_bounds(&self, pos: Vector3i) -> bool {
+        let Some((min, max)) = self.bounds else { return false; };
+
+        pos.x >= min.x && pos.x <= max.x &&
+        pos.z >= min.z && pos.z <= max.z &&
+        pos.y >= min.y && pos.y <= max.y + 1
+    }
+}
@@ -0,0 +1,33 @@
+// Small seeded generator so battles can be reproduced from a seed
+// SplitMix64, state is a plain u64 so it's trivial to save and restore
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct BattleRng {
+    pub state: u64,
+}
+
+impl BattleRng {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            state: seed,
+        }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+
+        let mut z: u64 = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    // Number in 0..100
+    pub fn roll_percent(&mut self) -> i32 {
+        (self.next_u64() % 100) as i32
+    }
+
+    // True with a chance out of 100
+    pub fn check(&mut self, chance: i32) -> bool {
+        self.roll_percent() < chance
+    }
+}
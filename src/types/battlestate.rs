@@ -0,0 +1,453 @@
+use crate::types::{get_board_checksum, get_strike_context, plan_turn, resolve_exchange, resolve_heal, AiAction, AiBehaviour, AiOrders, AiPlan, BattleGrid, BattleOutcome, BattleRng, ExchangeResult, HealResult, ObjectiveContext, Objectives, Occupants, ReachMap, StrikeContext, TurnState, UnitRanges, UnitState};
+
+use std::{collections::HashMap, rc::Rc};
+use godot::builtin::Vector3i;
+
+// Whole board as plain data, so battles can be run without any nodes
+// The field keeps one as the truth its nodes show, simulations just run on copies of it
+#[derive(Clone, Debug)]
+pub struct BattleState {
+    pub grid: Rc<BattleGrid>, // Shared, as blocks don't change during a battle
+    pub units: Vec<UnitState>, // Kept in id order so every run goes the same
+    pub orders: HashMap<u64, AiOrders>, // Units without orders are aggressive and guard where they are
+    pub turn: TurnState,
+    pub rng: BattleRng,
+    pub fall_damage_per_level: i32, // Damage for each level fallen past a unit's safe drop
+}
+
+// Where a move went, and what the landing did to the unit
+#[derive(Clone, PartialEq, Debug)]
+pub struct MoveResult {
+    pub path: Vec<Vector3i>,
+    pub fall_damage: i32,
+    pub killed: bool, // Taken off the board already
+}
+
+// How a simulated battle went
+#[derive(Clone, PartialEq, Debug)]
+pub struct SimResult {
+    pub outcome: Option<BattleOutcome>, // None if it ran out of turns first
+    pub turns: u32,
+}
+
+impl BattleState {
+    pub fn new(grid: Rc<BattleGrid>, seed: u64) -> Self {
+        Self {
+            grid,
+            units: Vec::new(),
+            orders: HashMap::new(),
+            turn: TurnState::new(),
+            rng: BattleRng::new(seed),
+            fall_damage_per_level: 0,
+        }
+    }
+
+    // Put a unit on the board, returns false if its cell is taken or its id is already used
+    pub fn add_unit(&mut self, unit: UnitState, orders: Option<AiOrders>) -> bool {
+        if self.units.iter().any(|other| other.id == unit.id || other.pos == unit.pos) { return false; }
+
+        self.turn.add_unit(unit.id, unit.chartype);
+        if let Some(orders) = orders {
+            self.orders.insert(unit.id, orders);
+        }
+
+        let index: usize = self.units.partition_point(|other| other.id < unit.id);
+        self.units.insert(index, unit);
+        true
+    }
+
+    pub fn remove_unit(&mut self, id: u64) {
+        self.units.retain(|unit| unit.id != id);
+        self.orders.remove(&id);
+        self.turn.remove_unit(id);
+    }
+
+    // Take every unit off, leaving the grid, turn and rng alone
+    pub fn clear_units(&mut self) {
+        for id in self.units.iter().map(|unit| unit.id).collect::<Vec<u64>>() {
+            self.remove_unit(id);
+        }
+    }
+
+    pub fn get_unit(&self, id: u64) -> Option<&UnitState> {
+        self.units.iter().find(|unit| unit.id == id)
+    }
+
+    pub fn get_unit_at(&self, pos: Vector3i) -> Option<&UnitState> {
+        self.units.iter().find(|unit| unit.pos == pos)
+    }
+
+    fn get_unit_mut(&mut self, id: u64) -> Option<&mut UnitState> {
+        self.units.iter_mut().find(|unit| unit.id == id)
+    }
+
+    pub fn get_occupants(&self) -> Occupants {
+        self.units.iter().map(|unit| (unit.pos, unit.chartype)).collect()
+    }
+
+    // Units that already moved this phase can't move again
+    pub fn get_move_range(&self, unit: &UnitState) -> u32 {
+        if self.turn.has_moved(unit.id) { 0 } else { unit.movement_range }
+    }
+
+    pub fn get_ranges(&self, unit: &UnitState) -> UnitRanges {
+        UnitRanges::build(&*self.grid, unit, self.get_move_range(unit), &self.get_occupants())
+    }
+
+    // Move a unit along the cheapest path to to, None if it can't end its movement there
+    // Falls too far for the unit hurt it on landing, and can kill it
+    pub fn move_unit(&mut self, id: u64, to: Vector3i) -> Option<MoveResult> {
+        let unit: &UnitState = self.get_unit(id)?;
+        let reachable: ReachMap = self.get_ranges(unit).reachable;
+
+        if to == reachable.origin || !reachable.can_stop_at(to) { return None; }
+        let path: Vec<Vector3i> = reachable.get_path(to)?;
+        let fall_damage: i32 = reachable.cells.get(&to)?.fall_levels as i32 * self.fall_damage_per_level;
+
+        let unit: &mut UnitState = self.get_unit_mut(id)?;
+        unit.pos = to;
+        unit.stats.hp = (unit.stats.hp - fall_damage).max(0);
+        let killed: bool = unit.stats.hp == 0;
+
+        if killed {
+            self.remove_unit(id);
+        } else {
+            self.turn.mark_moved(id);
+        }
+
+        Some(MoveResult { path, fall_damage, killed })
+    }
+
+    // Put a unit somewhere without it walking there, for changes made outside the rules
+    // Returns false if someone else is standing there
+    pub fn place_unit(&mut self, id: u64, pos: Vector3i) -> bool {
+        if self.get_unit_at(pos).is_some_and(|unit| unit.id != id) { return false; }
+        let Some(unit) = self.get_unit_mut(id) else { return false; };
+
+        unit.pos = pos;
+        true
+    }
+
+    // Take back a move, with the hp the unit had before any fall and its moved flag as it was
+    pub fn undo_move(&mut self, id: u64, from: Vector3i, hp: i32, moved_before: bool) -> bool {
+        if !self.place_unit(id, from) { return false; }
+        let Some(unit) = self.get_unit_mut(id) else { return false; };
+
+        unit.stats.hp = hp;
+        self.turn.set_flags(id, moved_before, false);
+        true
+    }
+
+    // Attack whoever is at target, None if they can't be attacked
+    // Dead units are taken off the board, a surviving attacker is spent
+    pub fn attack_unit(&mut self, id: u64, target: Vector3i) -> Option<ExchangeResult> {
+        let attacker: UnitState = self.get_unit(id)?.clone();
+        let defender: UnitState = self.get_unit_at(target)?.clone();
+
+        if !attacker.chartype.is_hostile_to(defender.chartype) { return None; }
+        if !attacker.get_attack_map_from(&*self.grid, attacker.pos).contains(&target) { return None; }
+
+        let context: StrikeContext = get_strike_context(&*self.grid, attacker.pos, attacker.profile.height, target);
+        let can_counter: bool = defender.weapon.max_range > 0 && defender.get_attack_map_from(&*self.grid, target).contains(&attacker.pos);
+        let counter_context: Option<StrikeContext> = can_counter
+            .then(|| get_strike_context(&*self.grid, target, defender.profile.height, attacker.pos));
+
+        let result: ExchangeResult = resolve_exchange(&attacker.stats, &defender.stats, &context, counter_context.as_ref(), &mut self.rng);
+
+        self.get_unit_mut(defender.id)?.stats.hp = result.attack.defender_hp;
+        if let Some(counter) = result.counter {
+            self.get_unit_mut(id)?.stats.hp = counter.defender_hp;
+        }
+
+        if result.attack.killed { self.remove_unit(defender.id); }
+
+        if result.counter.is_some_and(|counter| counter.killed) {
+            self.remove_unit(id);
+        } else {
+            self.turn.mark_acted(id);
+        }
+
+        Some(result)
+    }
+
+    // Heal whoever is at target, None if they can't be healed
+    pub fn heal_unit(&mut self, id: u64, target: Vector3i) -> Option<HealResult> {
+        let healer: UnitState = self.get_unit(id)?.clone();
+        let patient: UnitState = self.get_unit_at(target)?.clone();
+
+        if healer.heal_range == 0 { return None; }
+        if healer.chartype.is_hostile_to(patient.chartype) { return None; }
+        if !healer.get_heal_map_from(&*self.grid, healer.pos).contains(target) { return None; }
+
+        let result: HealResult = resolve_heal(&healer.stats, &patient.stats);
+        self.get_unit_mut(patient.id)?.stats.hp = result.target_hp;
+        self.turn.mark_acted(id);
+
+        Some(result)
+    }
+
+    // Returns false if the unit isn't on the board, like after a fall killed it
+    pub fn wait_unit(&mut self, id: u64) -> bool {
+        if self.get_unit(id).is_none() { return false; }
+
+        self.turn.mark_acted(id);
+        true
+    }
+
+    // Go to the next phase, returns true if that started a new turn
+    pub fn end_phase(&mut self) -> bool {
+        self.turn.advance_phase()
+    }
+
+    pub fn get_checksum(&self) -> u64 {
+        get_board_checksum(&self.units, self.turn.turn, self.turn.phase, self.rng.state)
+    }
+
+    pub fn get_objective_context(&self) -> ObjectiveContext<'_> {
+        ObjectiveContext {
+            turn: self.turn.turn,
+            units: &self.units,
+        }
+    }
+
+    pub fn get_orders(&self, unit: &UnitState) -> AiOrders {
+        self.orders.get(&unit.id).copied().unwrap_or(AiOrders {
+            behaviour: AiBehaviour::Aggressive,
+            home: unit.pos,
+            guard_radius: 0,
+            objective: None,
+        })
+    }
+
+    // What the AI would do with a unit, None if it isn't on the board
+    pub fn plan_unit(&self, id: u64) -> Option<AiPlan> {
+        let unit: &UnitState = self.get_unit(id)?;
+        let ranges: HashMap<u64, Rc<UnitRanges>> = self.units.iter()
+            .map(|unit| (unit.id, Rc::new(self.get_ranges(unit))))
+            .collect();
+
+        Some(plan_turn(&*self.grid, unit, &self.units, &self.get_orders(unit), &ranges))
+    }
+
+    // Take a unit's whole phase the way BattleAi does, moving then attacking, healing or waiting
+    pub fn run_ai_unit(&mut self, id: u64) {
+        let Some(plan) = self.plan_unit(id) else { return; };
+
+        if self.get_unit(id).is_some_and(|unit| unit.pos != plan.dest) {
+            self.move_unit(id, plan.dest);
+        }
+
+        let acted: bool = match plan.action {
+            Some(AiAction::Attack(target)) => self.attack_unit(id, target).is_some(),
+            Some(AiAction::Heal(target)) => self.heal_unit(id, target).is_some(),
+            None => false,
+        };
+        if !acted {
+            self.wait_unit(id);
+        }
+    }
+
+    // Let the AI take every unit of the current phase, stopping as soon as the battle is decided
+    pub fn run_ai_phase(&mut self, objectives: &mut Objectives) -> Option<BattleOutcome> {
+        for id in self.turn.get_phase_units() {
+            // Could have died to a counter since the phase began
+            if self.turn.is_spent(id) || self.get_unit(id).is_none() { continue; }
+
+            self.run_ai_unit(id);
+
+            let outcome: Option<BattleOutcome> = objectives.evaluate(&self.get_objective_context());
+            if outcome.is_some() { return outcome; }
+        }
+
+        None
+    }
+
+    // Play the battle out with the AI on every side, for balancing levels
+    // Objectives are checked as each phase begins and after every unit, like BattleObjectives does
+    pub fn simulate(&mut self, objectives: &mut Objectives, max_turns: u32) -> SimResult {
+        loop {
+            let mut outcome: Option<BattleOutcome> = objectives.evaluate(&self.get_objective_context());
+            if outcome.is_none() {
+                outcome = self.run_ai_phase(objectives);
+            }
+
+            // Phases can't advance with nobody left, so that's as far as it goes
+            let stuck: bool = !self.turn.has_units();
+            if outcome.is_some() || stuck || (self.end_phase() && self.turn.turn > max_turns) {
+                return SimResult {
+                    outcome,
+                    turns: self.turn.turn.min(max_turns),
+                };
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::testing::{cell, grid, unit};
+    use crate::types::CharType;
+
+    // Same as the shared unit, hitting for attack instead
+    fn fighter(id: u64, pos: Vector3i, chartype: CharType, hp: i32, attack: i32) -> UnitState {
+        let mut unit: UnitState = unit(id, pos, chartype, hp);
+        unit.stats.attack = attack;
+        unit
+    }
+
+    fn state(units: Vec<UnitState>) -> BattleState {
+        let mut state: BattleState = BattleState::new(Rc::new(grid(6)), 1);
+        for unit in units {
+            assert!(state.add_unit(unit, None));
+        }
+
+        state
+    }
+
+    #[test]
+    fn units_reach_as_far_as_their_movement_range() {
+        let state: BattleState = state(vec![unit(1, cell(0, 0), CharType::Player, 10)]);
+        let ranges: UnitRanges = state.get_ranges(state.get_unit(1).unwrap());
+
+        assert_eq!(ranges.reachable.get_cost(cell(3, 0)), Some(3));
+        assert_eq!(ranges.reachable.get_cost(cell(2, 1)), Some(3));
+        assert!(!ranges.reachable.contains(cell(4, 0)));
+        assert!(!ranges.reachable.contains(cell(2, 2)));
+    }
+
+    #[test]
+    fn allies_can_be_passed_but_not_stopped_on() {
+        let mut state: BattleState = state(vec![
+            unit(1, cell(0, 0), CharType::Player, 10),
+            unit(2, cell(1, 0), CharType::Ally, 10),
+        ]);
+
+        assert_eq!(state.move_unit(1, cell(1, 0)), None);
+        assert_eq!(state.move_unit(1, cell(2, 0)).unwrap().path, vec![cell(0, 0), cell(1, 0), cell(2, 0)]);
+        assert_eq!(state.get_unit(1).unwrap().pos, cell(2, 0));
+    }
+
+    #[test]
+    fn moved_units_cant_move_again() {
+        let mut state: BattleState = state(vec![unit(1, cell(0, 0), CharType::Player, 10)]);
+
+        assert!(state.move_unit(1, cell(1, 0)).is_some());
+        assert_eq!(state.get_move_range(state.get_unit(1).unwrap()), 0);
+        assert_eq!(state.move_unit(1, cell(2, 0)), None);
+    }
+
+    #[test]
+    fn undone_moves_put_the_unit_back_as_it_was() {
+        let mut state: BattleState = state(vec![unit(1, cell(0, 0), CharType::Player, 10)]);
+
+        state.move_unit(1, cell(2, 0));
+        assert!(state.undo_move(1, cell(0, 0), 10, false));
+
+        assert_eq!(state.get_unit(1).unwrap().pos, cell(0, 0));
+        assert!(state.move_unit(1, cell(1, 0)).is_some());
+    }
+
+    #[test]
+    fn falls_past_a_safe_drop_hurt_on_landing() {
+        let mut state: BattleState = state(vec![
+            unit(1, Vector3i::new(0, 4, 0), CharType::Player, 10),
+            unit(2, Vector3i::new(0, 4, 1), CharType::Player, 2),
+        ]);
+        state.fall_damage_per_level = 3;
+
+        // Block to stand on, with a drop of 3 down to the floor beside it
+        let blocks: &mut BattleGrid = Rc::make_mut(&mut state.grid);
+        blocks.set_cell(Vector3i::new(0, 3, 0), 0, 0);
+        blocks.set_cell(Vector3i::new(0, 3, 1), 0, 0);
+
+        let fall: MoveResult = state.move_unit(1, cell(1, 0)).unwrap();
+        assert!(fall.fall_damage > 0 && !fall.killed);
+        assert_eq!(state.get_unit(1).unwrap().stats.hp, 10 - fall.fall_damage);
+
+        assert!(state.move_unit(2, cell(1, 1)).unwrap().killed);
+        assert!(state.get_unit(2).is_none());
+        assert!(!state.wait_unit(2));
+    }
+
+    #[test]
+    fn attacks_trade_damage_and_spend_the_attacker() {
+        let mut state: BattleState = state(vec![
+            fighter(1, cell(0, 0), CharType::Player, 10, 4),
+            fighter(2, cell(1, 0), CharType::Enemy, 10, 3),
+        ]);
+
+        let result: ExchangeResult = state.attack_unit(1, cell(1, 0)).unwrap();
+
+        assert_eq!(result.attack.damage, 4);
+        assert_eq!(state.get_unit(2).unwrap().stats.hp, 6);
+        assert_eq!(state.get_unit(1).unwrap().stats.hp, 7);
+        assert!(state.turn.is_spent(1));
+    }
+
+    #[test]
+    fn killed_units_are_taken_off_the_board() {
+        let mut state: BattleState = state(vec![
+            unit(1, cell(0, 0), CharType::Player, 10),
+            fighter(2, cell(1, 0), CharType::Enemy, 5, 3),
+        ]);
+
+        let result: ExchangeResult = state.attack_unit(1, cell(1, 0)).unwrap();
+
+        assert!(result.attack.killed);
+        assert_eq!(result.counter, None);
+        assert!(state.get_unit(2).is_none());
+        assert_eq!(state.get_unit(1).unwrap().stats.hp, 10);
+    }
+
+    #[test]
+    fn only_hostiles_in_range_can_be_attacked() {
+        let mut state: BattleState = state(vec![
+            unit(1, cell(0, 0), CharType::Player, 10),
+            unit(2, cell(1, 0), CharType::Ally, 10),
+            unit(3, cell(3, 0), CharType::Enemy, 10),
+        ]);
+
+        assert_eq!(state.attack_unit(1, cell(1, 0)), None);
+        assert_eq!(state.attack_unit(1, cell(3, 0)), None);
+        assert!(!state.turn.is_spent(1));
+    }
+
+    #[test]
+    fn simulated_battles_play_out_to_the_end() {
+        let mut state: BattleState = state(vec![
+            unit(1, cell(0, 0), CharType::Player, 10),
+            fighter(2, cell(5, 0), CharType::Enemy, 10, 3),
+        ]);
+
+        // The player closes in, the enemy attacks and eats a counter, then the player finishes it
+        let result: SimResult = state.simulate(&mut Objectives::default(), 10);
+
+        assert_eq!(result.outcome, Some(BattleOutcome::Won("Every Enemy unit was defeated".to_string())));
+        assert_eq!(result.turns, 2);
+        assert_eq!(state.get_unit(1).unwrap().stats.hp, 7);
+        assert!(state.get_unit(2).is_none());
+    }
+
+    #[test]
+    fn simulations_stop_when_turns_run_out() {
+        let mut state: BattleState = state(vec![
+            fighter(1, cell(0, 0), CharType::Player, 10, 0),
+            fighter(2, cell(5, 5), CharType::Enemy, 10, 0),
+        ]);
+
+        let result: SimResult = state.simulate(&mut Objectives::default(), 3);
+
+        assert_eq!(result.outcome, None);
+        assert_eq!(result.turns, 3);
+    }
+
+    #[test]
+    fn simulations_with_nobody_on_the_board_stop_straight_away() {
+        let mut state: BattleState = state(Vec::new());
+
+        let result: SimResult = state.simulate(&mut Objectives::default(), 3);
+
+        assert_eq!(result, SimResult { outcome: None, turns: 1 });
+    }
+}
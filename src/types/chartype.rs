@@ -1,9 +1,16 @@
 use godot::{builtin::GString, prelude::{Export, GodotConvert, Var}};
 
-#[derive(GodotConvert, Var, Export)]
+#[derive(GodotConvert, Var, Export, Clone, Copy, PartialEq, Eq, Hash, Debug)]
 #[godot(via = GString)]
 pub enum CharType {
     Player,
     Ally,
     Enemy,
-}
\ No newline at end of file
+}
+
+impl CharType {
+    // Players and allies fight on the same side
+    pub fn is_hostile_to(&self, other: CharType) -> bool {
+        (*self == CharType::Enemy) != (other == CharType::Enemy)
+    }
+}
@@ -0,0 +1,334 @@
+use crate::constants::*;
+use crate::types::{get_cover, BattleRng, Cover, FieldCells};
+
+use godot::builtin::Vector3i;
+
+// Everything combat needs to know about a unit
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct UnitStats {
+    pub max_hp: i32,
+    pub hp: i32,
+    pub attack: i32,
+    pub defence: i32,
+    pub accuracy: i32,
+    pub evasion: i32,
+    pub critical: i32,
+    pub heal_power: i32,
+}
+
+// Where a strike is made from, relative to who it's aimed at
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct StrikeContext {
+    pub height_advantage: i32, // Levels the striker is above the target, negative if below
+    pub terrain_defence: i32,  // Bonus defence from the block the target stands on
+    pub cover: Cover,
+}
+
+// What a single strike would do before rolling for it
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct StrikeForecast {
+    pub damage: i32, // On a normal hit
+    pub hit_chance: i32,
+    pub crit_chance: i32,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct AttackResult {
+    pub hit: bool,
+    pub crit: bool,
+    pub damage: i32,     // 0 on a miss
+    pub defender_hp: i32,
+    pub killed: bool,
+}
+
+// An attack and the counter it might provoke
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct ExchangeResult {
+    pub attack: AttackResult,
+    pub counter: Option<AttackResult>, // Attacker is the defender of the counter
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct ExchangeForecast {
+    pub attack: StrikeForecast,
+    pub counter: Option<StrikeForecast>,
+    pub attacker_hp: i32, // If every strike hits without a crit
+    pub defender_hp: i32,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct HealResult {
+    pub amount: i32, // Only what was actually restored
+    pub target_hp: i32,
+}
+
+// Height, terrain and cover modifiers for a strike from one standing cell at another
+pub fn get_strike_context<F: FieldCells>(field: &F, from: Vector3i, striker_height: i32, target: Vector3i) -> StrikeContext {
+    StrikeContext {
+        height_advantage: from.y - target.y,
+        terrain_defence: field.get_terrain_defence(target + Vector3i::DOWN),
+        cover: get_cover(field, from, striker_height, target),
+    }
+}
+
+// Damage dealt on a normal hit, never negative
+pub fn get_damage(attacker: &UnitStats, defender: &UnitStats, context: &StrikeContext) -> i32 {
+    let attack: i32 = attacker.attack + context.height_advantage * HEIGHT_DAMAGE_BONUS_PER_LEVEL;
+    let defence: i32 = defender.defence + context.terrain_defence;
+
+    (attack - defence).max(0)
+}
+
+// Chance out of 100 to hit
+pub fn get_hit_chance(attacker: &UnitStats, defender: &UnitStats, context: &StrikeContext) -> i32 {
+    let cover_penalty: i32 = if context.cover == Cover::Partial { PARTIAL_COVER_HIT_PENALTY } else { 0 };
+
+    (attacker.accuracy - defender.evasion + context.height_advantage * HEIGHT_HIT_BONUS_PER_LEVEL - cover_penalty).clamp(0, 100)
+}
+
+// Chance out of 100 for a hit to be critical
+pub fn get_crit_chance(attacker: &UnitStats) -> i32 {
+    attacker.critical.clamp(0, 100)
+}
+
+pub fn get_strike(attacker: &UnitStats, defender: &UnitStats, context: &StrikeContext) -> StrikeForecast {
+    StrikeForecast {
+        damage: get_damage(attacker, defender, context),
+        hit_chance: get_hit_chance(attacker, defender, context),
+        crit_chance: get_crit_chance(attacker),
+    }
+}
+
+impl StrikeForecast {
+    // Average damage over every possible roll
+    pub fn get_expected_damage(&self) -> f32 {
+        let crit_bonus: f32 = self.crit_chance as f32 / 100.0 * (CRIT_DAMAGE_MULTIPLIER - 1) as f32;
+
+        self.damage as f32 * self.hit_chance as f32 / 100.0 * (1.0 + crit_bonus)
+    }
+}
+
+pub fn resolve_attack(attacker: &UnitStats, defender: &UnitStats, context: &StrikeContext, rng: &mut BattleRng) -> AttackResult {
+    let strike: StrikeForecast = get_strike(attacker, defender, context);
+
+    // Always roll for both so the number of rolls doesn't depend on the outcome
+    let hit: bool = rng.check(strike.hit_chance);
+    let crit: bool = rng.check(strike.crit_chance) && hit;
+
+    let damage: i32 = match (hit, crit) {
+        (false, _) => 0,
+        (true, false) => strike.damage,
+        (true, true) => strike.damage * CRIT_DAMAGE_MULTIPLIER,
+    };
+    let defender_hp: i32 = (defender.hp - damage).max(0);
+
+    AttackResult {
+        hit,
+        crit,
+        damage,
+        defender_hp,
+        killed: defender_hp == 0,
+    }
+}
+
+// Defender strikes back if it survives and counter_context is given (attacker is in its range)
+pub fn resolve_exchange(
+    attacker: &UnitStats, defender: &UnitStats, context: &StrikeContext, counter_context: Option<&StrikeContext>, rng: &mut BattleRng,
+) -> ExchangeResult {
+    let attack: AttackResult = resolve_attack(attacker, defender, context, rng);
+
+    let counter: Option<AttackResult> = match counter_context {
+        Some(counter_context) if !attack.killed => {
+            let defender: UnitStats = UnitStats { hp: attack.defender_hp, ..*defender };
+            Some(resolve_attack(&defender, attacker, counter_context, rng))
+        }
+        _ => None,
+    };
+
+    ExchangeResult {
+        attack,
+        counter,
+    }
+}
+
+pub fn forecast_exchange(
+    attacker: &UnitStats, defender: &UnitStats, context: &StrikeContext, counter_context: Option<&StrikeContext>,
+) -> ExchangeForecast {
+    let attack: StrikeForecast = get_strike(attacker, defender, context);
+    let defender_hp: i32 = (defender.hp - attack.damage).max(0);
+
+    let counter: Option<StrikeForecast> = counter_context.map(|counter_context| get_strike(defender, attacker, counter_context));
+
+    // A defender killed by the hit doesn't get to counter
+    let counter_damage: i32 = if defender_hp > 0 { counter.map_or(0, |counter| counter.damage) } else { 0 };
+    let attacker_hp: i32 = (attacker.hp - counter_damage).max(0);
+
+    ExchangeForecast {
+        attack,
+        counter,
+        attacker_hp,
+        defender_hp,
+    }
+}
+
+// Heals always land, but can't go past max hp
+pub fn resolve_heal(healer: &UnitStats, target: &UnitStats) -> HealResult {
+    let target_hp: i32 = (target.hp + healer.heal_power.max(0)).min(target.max_hp);
+
+    HealResult {
+        amount: target_hp - target.hp,
+        target_hp,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stats(hp: i32, attack: i32, defence: i32) -> UnitStats {
+        UnitStats {
+            max_hp: hp,
+            hp,
+            attack,
+            defence,
+            accuracy: 100,
+            evasion: 0,
+            critical: 0,
+            heal_power: 0,
+        }
+    }
+
+    #[test]
+    fn terrain_defence_adds_to_defence() {
+        let context: StrikeContext = StrikeContext { terrain_defence: 2, ..Default::default() };
+
+        assert_eq!(get_damage(&stats(10, 10, 0), &stats(10, 0, 3), &StrikeContext::default()), 7);
+        assert_eq!(get_damage(&stats(10, 10, 0), &stats(10, 0, 3), &context), 5);
+    }
+
+    #[test]
+    fn damage_never_goes_below_zero() {
+        let context: StrikeContext = StrikeContext { terrain_defence: 4, ..Default::default() };
+
+        assert_eq!(get_damage(&stats(10, 2, 0), &stats(10, 0, 5), &context), 0);
+    }
+
+    #[test]
+    fn height_helps_damage_and_hit() {
+        let attacker: UnitStats = UnitStats { accuracy: 70, ..stats(10, 5, 0) };
+        let defender: UnitStats = UnitStats { evasion: 10, ..stats(10, 0, 0) };
+        let above: StrikeContext = StrikeContext { height_advantage: 2, ..Default::default() };
+        let below: StrikeContext = StrikeContext { height_advantage: -2, ..Default::default() };
+
+        assert_eq!(get_damage(&attacker, &defender, &above), 5 + 2 * HEIGHT_DAMAGE_BONUS_PER_LEVEL);
+        assert_eq!(get_hit_chance(&attacker, &defender, &above), 60 + 2 * HEIGHT_HIT_BONUS_PER_LEVEL);
+        assert_eq!(get_hit_chance(&attacker, &defender, &below), 60 - 2 * HEIGHT_HIT_BONUS_PER_LEVEL);
+    }
+
+    #[test]
+    fn hit_chance_is_clamped_and_lowered_by_cover() {
+        let attacker: UnitStats = UnitStats { accuracy: 150, ..stats(10, 5, 0) };
+        let partial: StrikeContext = StrikeContext { cover: Cover::Partial, ..Default::default() };
+
+        assert_eq!(get_hit_chance(&attacker, &stats(10, 0, 0), &StrikeContext::default()), 100);
+        assert_eq!(get_hit_chance(&attacker, &UnitStats { evasion: 200, ..stats(10, 0, 0) }, &partial), 0);
+        assert_eq!(get_hit_chance(&stats(10, 5, 0), &stats(10, 0, 0), &partial), 100 - PARTIAL_COVER_HIT_PENALTY);
+    }
+
+    #[test]
+    fn crits_multiply_damage() {
+        let attacker: UnitStats = UnitStats { critical: 100, ..stats(10, 4, 0) };
+        let mut rng: BattleRng = BattleRng::new(7);
+
+        let result: AttackResult = resolve_attack(&attacker, &stats(20, 0, 1), &StrikeContext::default(), &mut rng);
+
+        assert!(result.hit && result.crit);
+        assert_eq!(result.damage, 3 * CRIT_DAMAGE_MULTIPLIER);
+        assert_eq!(result.defender_hp, 20 - 3 * CRIT_DAMAGE_MULTIPLIER);
+    }
+
+    #[test]
+    fn misses_deal_nothing_and_still_roll_twice() {
+        let attacker: UnitStats = UnitStats { accuracy: 0, critical: 100, ..stats(10, 4, 0) };
+        let mut rng: BattleRng = BattleRng::new(7);
+        let mut expected_rng: BattleRng = BattleRng::new(7);
+        expected_rng.next_u64();
+        expected_rng.next_u64();
+
+        let result: AttackResult = resolve_attack(&attacker, &stats(20, 0, 0), &StrikeContext::default(), &mut rng);
+
+        assert!(!result.hit && !result.crit);
+        assert_eq!(result.damage, 0);
+        assert_eq!(result.defender_hp, 20);
+        assert_eq!(rng, expected_rng);
+    }
+
+    #[test]
+    fn same_seed_gives_same_result() {
+        let attacker: UnitStats = UnitStats { accuracy: 60, critical: 30, ..stats(10, 6, 0) };
+        let defender: UnitStats = stats(30, 0, 1);
+
+        let results: Vec<Vec<AttackResult>> = (0..2).map(|_| {
+            let mut rng: BattleRng = BattleRng::new(1234);
+            (0..20).map(|_| resolve_attack(&attacker, &defender, &StrikeContext::default(), &mut rng)).collect()
+        }).collect();
+
+        assert_eq!(results[0], results[1]);
+    }
+
+    #[test]
+    fn killed_defenders_stop_at_zero_and_never_counter() {
+        let mut rng: BattleRng = BattleRng::new(7);
+        let counter_context: StrikeContext = StrikeContext::default();
+
+        let result: ExchangeResult = resolve_exchange(
+            &stats(10, 10, 0), &stats(4, 10, 0), &StrikeContext::default(), Some(&counter_context), &mut rng,
+        );
+
+        assert!(result.attack.killed);
+        assert_eq!(result.attack.defender_hp, 0);
+        assert_eq!(result.counter, None);
+    }
+
+    #[test]
+    fn survivors_counter_with_their_new_hp() {
+        let mut rng: BattleRng = BattleRng::new(7);
+        let counter_context: StrikeContext = StrikeContext::default();
+
+        let result: ExchangeResult = resolve_exchange(
+            &stats(10, 3, 0), &stats(10, 4, 0), &StrikeContext::default(), Some(&counter_context), &mut rng,
+        );
+
+        assert_eq!(result.attack.defender_hp, 7);
+        assert_eq!(result.counter.map(|counter| counter.defender_hp), Some(6));
+    }
+
+    #[test]
+    fn forecast_skips_the_counter_of_a_defender_it_kills() {
+        let counter_context: StrikeContext = StrikeContext::default();
+
+        let forecast: ExchangeForecast = forecast_exchange(&stats(10, 3, 0), &stats(3, 5, 0), &StrikeContext::default(), Some(&counter_context));
+
+        assert_eq!(forecast.defender_hp, 0);
+        assert_eq!(forecast.attacker_hp, 10);
+    }
+
+    #[test]
+    fn heals_stop_at_max_hp() {
+        let healer: UnitStats = UnitStats { heal_power: 10, ..stats(10, 0, 0) };
+        let target: UnitStats = UnitStats { hp: 8, ..stats(10, 0, 0) };
+
+        let result: HealResult = resolve_heal(&healer, &target);
+
+        assert_eq!(result.amount, 2);
+        assert_eq!(result.target_hp, 10);
+    }
+
+    #[test]
+    fn negative_heal_power_does_nothing() {
+        let healer: UnitStats = UnitStats { heal_power: -5, ..stats(10, 0, 0) };
+        let target: UnitStats = UnitStats { hp: 5, ..stats(10, 0, 0) };
+
+        assert_eq!(resolve_heal(&healer, &target), HealResult { amount: 0, target_hp: 5 });
+    }
+}
@@ -0,0 +1,59 @@
+use std::collections::{HashMap, HashSet};
+use godot::builtin::Vector3i;
+
+// Kinds of highlight, lowest priority first
+// A cell in several layers shows the highest one
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum HighlightLayer {
+    Danger,
+    Heal,
+    Attack,
+    Move,
+    Fall,
+    Path,
+    PathEnd,
+    Hover,
+}
+
+impl HighlightLayer {
+    pub const ALL: [HighlightLayer; 8] = [
+        HighlightLayer::Danger,
+        HighlightLayer::Heal,
+        HighlightLayer::Attack,
+        HighlightLayer::Move,
+        HighlightLayer::Fall,
+        HighlightLayer::Path,
+        HighlightLayer::PathEnd,
+        HighlightLayer::Hover,
+    ];
+}
+
+// Every highlighted cell on each layer, kept apart so clearing one layer uncovers the ones under it
+#[derive(Clone, Debug, Default)]
+pub struct HighlightLayers {
+    layers: HashMap<HighlightLayer, HashSet<Vector3i>>,
+}
+
+impl HighlightLayers {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Replace a layer's cells, returns every cell whose highlight may have changed
+    pub fn set_layer(&mut self, layer: HighlightLayer, cells: impl IntoIterator<Item = Vector3i>) -> Vec<Vector3i> {
+        let cells: HashSet<Vector3i> = cells.into_iter().collect();
+        let old_cells: HashSet<Vector3i> = self.layers.insert(layer, cells).unwrap_or_default();
+        let new_cells: &HashSet<Vector3i> = self.layers.get(&layer).expect("Cannot fail as it was just inserted");
+
+        old_cells.symmetric_difference(new_cells).copied().collect()
+    }
+
+    pub fn contains(&self, layer: HighlightLayer, pos: Vector3i) -> bool {
+        self.layers.get(&layer).is_some_and(|cells| cells.contains(&pos))
+    }
+
+    // Layer that should be drawn at pos, None if it isn't highlighted
+    pub fn get_top(&self, pos: Vector3i) -> Option<HighlightLayer> {
+        HighlightLayer::ALL.into_iter().rev().find(|layer| self.contains(*layer, pos))
+    }
+}
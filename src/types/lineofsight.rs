@@ -0,0 +1,91 @@
+use crate::constants::*;
+use crate::types::FieldCells;
+
+use godot::{builtin::{GString, Vector3, Vector3i}, prelude::{Export, GodotConvert, Var}};
+
+// How much of a target a shooter can see
+#[derive(GodotConvert, Var, Export, Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+#[godot(via = GString)]
+pub enum Cover {
+    #[default]
+    None,
+    Partial, // Harder to hit
+    Full,    // Can't be targeted at all
+}
+
+// What a single ray ran into on its way to the target
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum RayHit {
+    Clear,
+    Grazed,  // Passed through a slope or stairs, which only fill part of their cell
+    Blocked,
+}
+
+// Cover of a target standing at target from a shooter standing at from
+// Rays are cast from the shooter's eyes to the target's legs and head, full cover only if both are blocked
+// Units never block sight, only blocks do
+pub fn get_cover<F: FieldCells>(field: &F, from: Vector3i, shooter_height: i32, target: Vector3i) -> Cover {
+    // Melee strikes reach around anything
+    if (from.x - target.x).abs() + (from.z - target.z).abs() <= 1 { return Cover::None; }
+
+    let eye: Vector3 = get_cell_point(from, (shooter_height - 1) as f32 + LOS_EYE_HEIGHT);
+    let legs: RayHit = trace_ray(field, eye, get_cell_point(target, LOS_TARGET_LOW));
+    let head: RayHit = trace_ray(field, eye, get_cell_point(target, LOS_TARGET_HIGH));
+
+    match (legs, head) {
+        (RayHit::Blocked, RayHit::Blocked) => Cover::Full,
+        (RayHit::Clear, RayHit::Clear) => Cover::None,
+        _ => Cover::Partial,
+    }
+}
+
+// Point in the middle of a cell horizontally, height up from its bottom
+fn get_cell_point(cell: Vector3i, height: f32) -> Vector3 {
+    Vector3::new(cell.x as f32 + 0.5, cell.y as f32 + height, cell.z as f32 + 0.5)
+}
+
+// Walk every cell the segment from start to end passes through, in order (voxel DDA)
+// Cells the segment starts and ends in are never checked
+fn trace_ray<F: FieldCells>(field: &F, start: Vector3, end: Vector3) -> RayHit {
+    let start: [f32; 3] = [start.x, start.y, start.z];
+    let dir: [f32; 3] = [end.x - start[0], end.y - start[1], end.z - start[2]];
+    let end_cell: [i32; 3] = [end.x.floor() as i32, end.y.floor() as i32, end.z.floor() as i32];
+
+    let mut cell: [i32; 3] = [start[0].floor() as i32, start[1].floor() as i32, start[2].floor() as i32];
+    let mut step: [i32; 3] = [0; 3];
+    let mut t_max: [f32; 3] = [f32::INFINITY; 3]; // How far along the segment the next boundary on each axis is
+    let mut t_delta: [f32; 3] = [f32::INFINITY; 3]; // How far along the segment one whole cell on each axis is
+
+    for axis in 0..3 {
+        if dir[axis] > 0.0 {
+            step[axis] = 1;
+            t_max[axis] = ((cell[axis] + 1) as f32 - start[axis]) / dir[axis];
+            t_delta[axis] = 1.0 / dir[axis];
+        } else if dir[axis] < 0.0 {
+            step[axis] = -1;
+            t_max[axis] = (cell[axis] as f32 - start[axis]) / dir[axis];
+            t_delta[axis] = -1.0 / dir[axis];
+        }
+    }
+
+    let mut hit: RayHit = RayHit::Clear;
+
+    loop {
+        // Step over the nearest boundary
+        let axis: usize = (0..3).min_by(|a, b| t_max[*a].total_cmp(&t_max[*b])).expect("Cannot fail as range isn't empty");
+        if t_max[axis] > 1.0 { break; }
+
+        cell[axis] += step[axis];
+        t_max[axis] += t_delta[axis];
+
+        if cell == end_cell { break; }
+
+        let pos: Vector3i = Vector3i::new(cell[0], cell[1], cell[2]);
+        if field.is_empty(pos) { continue; }
+
+        if field.get_ramp_dir(pos).is_none() { return RayHit::Blocked; }
+        hit = RayHit::Grazed;
+    }
+
+    hit
+}
@@ -1,5 +1,45 @@
-mod vectree;
+mod reachmap;
 mod chartype;
+mod terraincosts;
+mod movementclass;
+mod movement;
+mod turnstate;
+mod battlerng;
+mod lineofsight;
+mod weaponrange;
+mod combat;
+mod unitstate;
+mod unitranges;
+mod ai;
+mod highlightlayers;
+mod victorycondition;
+mod objectives;
+mod savedata;
+mod battlecommand;
+mod replay;
+mod battlegrid;
+mod battlestate;
+#[cfg(test)]
+mod testing;
 
-pub use vectree::VecTree;
-pub use chartype::CharType;
\ No newline at end of file
+pub use reachmap::{ReachMap, ReachCell, ReachStep};
+pub use chartype::CharType;
+pub use terraincosts::TerrainCosts;
+pub use movementclass::{MovementClass, MovementProfile};
+pub use movement::{FieldCells, Mover, Occupants, DIRECTIONS, get_steps, rotate_by_orientation};
+pub use turnstate::{TurnState, PHASE_ORDER};
+pub use battlerng::BattleRng;
+pub use weaponrange::{WeaponShape, WeaponRange};
+pub use lineofsight::{Cover, get_cover};
+pub use combat::{UnitStats, StrikeContext, StrikeForecast, AttackResult, ExchangeResult, ExchangeForecast, HealResult, get_strike, get_strike_context, resolve_attack, resolve_exchange, forecast_exchange, resolve_heal};
+pub use unitstate::UnitState;
+pub use unitranges::{UnitRanges, RangeCache};
+pub use ai::{AiBehaviour, AiWeights, AiOrders, AiAction, AiPlan, plan_turn, score_attack, score_position, get_distance};
+pub use highlightlayers::{HighlightLayer, HighlightLayers};
+pub use victorycondition::VictoryCondition;
+pub use objectives::{Condition, ConditionKind, ObjectiveContext, Objectives, BattleOutcome};
+pub use savedata::read_save_value;
+pub use battlecommand::{BattleCommand, ActionLog};
+pub use replay::{ReplayStep, ReplayLog, REPLAY_STEP_LEN, get_board_checksum};
+pub use battlegrid::BattleGrid;
+pub use battlestate::{BattleState, MoveResult, SimResult};
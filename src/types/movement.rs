@@ -0,0 +1,148 @@
+use crate::types::{CharType, MovementClass, MovementProfile, ReachStep};
+
+use std::collections::HashMap;
+use godot::{builtin::Vector3i, classes::GridMap};
+
+// The 4 tiles around any tile
+pub const DIRECTIONS: [Vector3i; 4] = [Vector3i::RIGHT, Vector3i::LEFT, Vector3i::BACK, Vector3i::FORWARD];
+
+// Type of whoever is standing in each cell
+pub type Occupants = HashMap<Vector3i, CharType>;
+
+// Who is moving, and who might be in their way
+// Profile is the class profile with any per unit changes applied
+pub struct Mover<'a> {
+    pub class: MovementClass,
+    pub profile: MovementProfile,
+    pub chartype: CharType,
+    pub occupants: &'a Occupants,
+}
+
+// Read access to the blocks of a field, so movement rules don't depend on GridMap
+pub trait FieldCells {
+    // GridMap::INVALID_CELL_ITEM for empty cells
+    fn get_cell_item(&self, pos: Vector3i) -> i32;
+    // Direction from the low edge to the high edge if pos is a slope or stairs
+    fn get_ramp_dir(&self, pos: Vector3i) -> Option<Vector3i>;
+    // Cost of standing on the block at pos, None if impassable
+    fn get_terrain_cost_for(&self, pos: Vector3i, class: MovementClass) -> Option<u32>;
+    // Whether pos is in the space the field's blocks take up, with room to stand on the top layer
+    fn is_in_bounds(&self, pos: Vector3i) -> bool;
+    // Defence bonus for standing on the block at pos
+    fn get_terrain_defence(&self, pos: Vector3i) -> i32;
+
+    fn is_empty(&self, pos: Vector3i) -> bool {
+        self.get_cell_item(pos) == GridMap::INVALID_CELL_ITEM
+    }
+}
+
+// Rotate dir by one of GridMap's orthogonal orientation indices
+// Only rotations around Y keep a ramp walkable, anything else gives None
+pub fn rotate_by_orientation(orientation: i32, dir: Vector3i) -> Option<Vector3i> {
+    match orientation {
+        0 => Some(dir),
+        10 => Some(Vector3i::new(-dir.x, dir.y, -dir.z)),
+        16 => Some(Vector3i::new(dir.z, dir.y, -dir.x)),
+        22 => Some(Vector3i::new(-dir.z, dir.y, dir.x)),
+        _ => None,
+    }
+}
+
+// Every step a unit can take from the cell it's standing in
+pub fn get_steps<F: FieldCells>(field: &F, mover: &Mover, pos: Vector3i) -> Vec<ReachStep> {
+    DIRECTIONS.iter()
+        .filter_map(|dir| get_step_into(field, mover.class, &mover.profile, pos, *dir))
+        .filter_map(|step| apply_occupants(mover, step))
+        .collect()
+}
+
+// Allies can be passed through but not stopped on, enemies block
+// Stepping next to an enemy ends movement (zone of control)
+fn apply_occupants(mover: &Mover, mut step: ReachStep) -> Option<ReachStep> {
+    if let Some(occupant) = mover.occupants.get(&step.pos) {
+        if occupant.is_hostile_to(mover.chartype) { return None; }
+
+        step.can_stop = false;
+    }
+
+    step.halts = is_in_zone_of_control(mover, step.pos);
+
+    // Can neither stay nor go on, so not worth stepping into
+    if step.halts && !step.can_stop { return None; }
+
+    Some(step)
+}
+
+// Whether a hostile unit is right next to pos, allowing a level of difference for slopes
+fn is_in_zone_of_control(mover: &Mover, pos: Vector3i) -> bool {
+    DIRECTIONS.iter().any(|dir| {
+        (-1..=1).any(|level| {
+            mover.occupants.get(&(pos + *dir + Vector3i::UP * level))
+                .is_some_and(|occupant| occupant.is_hostile_to(mover.chartype))
+        })
+    })
+}
+
+// Where a unit standing at pos ends up when stepping in dir
+fn get_step_into<F: FieldCells>(field: &F, class: MovementClass, profile: &MovementProfile, pos: Vector3i, dir: Vector3i) -> Option<ReachStep> {
+    let column: Vector3i = pos + dir;
+    if !field.is_in_bounds(column) { return None; }
+
+    // Ramps are walked up from the low edge to the high edge and down the other way,
+    // so they can only be left over the low or high edge
+    let get_ramp_dir = |pos: Vector3i| if profile.ignores_slopes { None } else { field.get_ramp_dir(pos) };
+    if let Some(ramp_dir) = get_ramp_dir(pos + Vector3i::DOWN) {
+        if dir != ramp_dir && dir != -ramp_dir { return None; }
+    }
+
+    let mut drop: i32 = 0;
+    let mut ramped_up: bool = false;
+    let mut climbed: bool = false;
+    let target: Vector3i = if !field.is_empty(column) {
+        if get_ramp_dir(column) == Some(dir) {
+            // Entered from the low edge, so go up a level
+            ramped_up = true;
+            column + Vector3i::UP
+        } else {
+            // Climb to the first empty cell above the block
+            climbed = true;
+            (1..=profile.climb_height)
+                .map(|climb| column + Vector3i::UP * climb)
+                .find(|cell| field.is_empty(*cell))?
+        }
+    } else {
+        // Drop down to the first floor below, never searching past the bottom of the field
+        let landing: Option<i32> = (0..=profile.drop_height)
+            .take_while(|drop| field.is_in_bounds(column + Vector3i::DOWN * *drop))
+            .find(|drop| !field.is_empty(column + Vector3i::DOWN * (*drop + 1)));
+
+        match landing {
+            Some(landing) => {
+                drop = landing;
+                column + Vector3i::DOWN * landing
+            }
+            // No floor in reach, so only pass over if the class can
+            None if profile.crosses_gaps && has_headroom(field, profile, column) => {
+                return Some(ReachStep { pos: column, cost: 1, can_stop: false, halts: false, fall_levels: 0 });
+            }
+            None => return None,
+        }
+    };
+
+    if !field.is_in_bounds(target) { return None; }
+    if !has_headroom(field, profile, target) { return None; }
+
+    // Getting onto a ramp from level or above has to be over its high edge, and it can't be climbed
+    if !ramped_up && get_ramp_dir(target + Vector3i::DOWN).is_some_and(|ramp_dir| climbed || dir != -ramp_dir) { return None; }
+
+    // Cost comes from the block being stood on
+    let cost: u32 = field.get_terrain_cost_for(target + Vector3i::DOWN, class)?;
+    let fall_levels: u32 = (drop - profile.safe_drop).max(0) as u32;
+
+    Some(ReachStep { pos: target, cost, can_stop: true, halts: false, fall_levels })
+}
+
+// Whether the unit fits standing at pos
+fn has_headroom<F: FieldCells>(field: &F, profile: &MovementProfile, pos: Vector3i) -> bool {
+    (0..profile.height).all(|level| field.is_empty(pos + Vector3i::UP * level))
+}
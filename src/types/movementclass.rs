@@ -0,0 +1,63 @@
+use godot::{builtin::GString, prelude::{Export, GodotConvert, Var}};
+
+#[derive(GodotConvert, Var, Export, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[godot(via = GString)]
+pub enum MovementClass {
+    Infantry,
+    Mech,
+    Flyer,
+    Hover,
+}
+
+// How a movement class gets around the field
+// Terrain cost overrides per class are set on the field, since block indices are level specific
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct MovementProfile {
+    pub height: i32,          // Empty cells needed to stand somewhere
+    pub climb_height: i32,    // Levels that can be stepped up without a slope
+    pub safe_drop: i32,       // Levels that can be dropped without taking damage
+    pub drop_height: i32,     // Levels that can be dropped at all, taking damage past safe_drop
+    pub ignores_slopes: bool, // Slopes are climbed like any other block instead of used as ramps
+    pub crosses_gaps: bool,   // Can pass over cells with no floor in drop range, but not stop there
+}
+
+impl MovementClass {
+    pub const ALL: [MovementClass; 4] = [MovementClass::Infantry, MovementClass::Mech, MovementClass::Flyer, MovementClass::Hover];
+
+    pub fn get_profile(&self) -> MovementProfile {
+        match self {
+            MovementClass::Infantry => MovementProfile {
+                height: 1,
+                climb_height: 1,
+                safe_drop: 1,
+                drop_height: 3,
+                ignores_slopes: false,
+                crosses_gaps: false,
+            },
+            MovementClass::Mech => MovementProfile {
+                height: 2,
+                climb_height: 0,
+                safe_drop: 1,
+                drop_height: 1,
+                ignores_slopes: false,
+                crosses_gaps: false,
+            },
+            MovementClass::Flyer => MovementProfile {
+                height: 1,
+                climb_height: 4,
+                safe_drop: 4,
+                drop_height: 4,
+                ignores_slopes: true,
+                crosses_gaps: true,
+            },
+            MovementClass::Hover => MovementProfile {
+                height: 1,
+                climb_height: 0,
+                safe_drop: 1,
+                drop_height: 2,
+                ignores_slopes: false,
+                crosses_gaps: true,
+            },
+        }
+    }
+}
@@ -0,0 +1,337 @@
+use crate::types::{CharType, UnitState};
+
+use std::collections::HashSet;
+use godot::{builtin::{GString, Vector3i}, prelude::{Export, GodotConvert, Var}};
+
+// Kinds of Condition, for declaring them in levels
+#[derive(GodotConvert, Var, Export, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[godot(via = GString)]
+pub enum ConditionKind {
+    All,
+    Any,
+    Routed,
+    UnitLost,
+    ReachCell,
+    HoldCells,
+    TurnPassed,
+}
+
+// Everything a condition looks at, taken fresh each time they're checked
+pub struct ObjectiveContext<'a> {
+    pub turn: u32,
+    pub units: &'a [UnitState],
+}
+
+// Something that can happen in a battle, built up with All and Any into whole objectives
+// Some remember what they've seen, so keep the same ones for the whole battle
+#[derive(Clone, PartialEq, Debug)]
+pub enum Condition {
+    Routed { side: CharType, seen: bool },      // Side had units and has none left
+    UnitLost { tag: String, seen: bool },       // Tagged unit was on the field and is gone
+    ReachCell { cells: Vec<Vector3i>, tag: Option<String> }, // Tagged unit, or any player unit, stands on one of the cells
+    HoldCells { cells: Vec<Vector3i>, turns: u32, held: u32, counted_turn: u32 }, // Players and allies start turns on every cell
+    TurnPassed(u32),                            // That turn is over
+    All(Vec<Condition>),
+    Any(Vec<Condition>),
+}
+
+impl Condition {
+    pub fn routed(side: CharType) -> Self {
+        Condition::Routed { side, seen: false }
+    }
+
+    pub fn unit_lost(tag: String) -> Self {
+        Condition::UnitLost { tag, seen: false }
+    }
+
+    pub fn hold_cells(cells: Vec<Vector3i>, turns: u32) -> Self {
+        Condition::HoldCells { cells, turns, held: 0, counted_turn: 0 }
+    }
+
+    // Take in what's happened since last time, call once per check before get_reason
+    pub fn update(&mut self, context: &ObjectiveContext) {
+        match self {
+            Condition::Routed { side, seen } => {
+                *seen |= context.units.iter().any(|unit| unit.chartype == *side);
+            }
+            Condition::UnitLost { tag, seen } => {
+                *seen |= context.units.iter().any(|unit| unit.tag == *tag);
+            }
+            // Counted the first time each turn is seen, so every enemy phase has had a go at the cells
+            Condition::HoldCells { cells, held, counted_turn, .. } => {
+                if context.turn == *counted_turn { return; }
+                *counted_turn = context.turn;
+
+                let player_cells: HashSet<Vector3i> = context.units.iter()
+                    .filter(|unit| unit.chartype != CharType::Enemy)
+                    .map(|unit| unit.pos)
+                    .collect();
+
+                *held = if cells.iter().all(|cell| player_cells.contains(cell)) { *held + 1 } else { 0 };
+            }
+            Condition::All(conditions) | Condition::Any(conditions) => {
+                for condition in conditions {
+                    condition.update(context);
+                }
+            }
+            Condition::ReachCell { .. } | Condition::TurnPassed(_) => {}
+        }
+    }
+
+    // Whatever update has built up, flattened in a fixed order so it can be saved
+    pub fn get_progress(&self, progress: &mut Vec<i64>) {
+        match self {
+            Condition::Routed { seen, .. } | Condition::UnitLost { seen, .. } => progress.push(*seen as i64),
+            Condition::HoldCells { held, counted_turn, .. } => progress.extend([*held as i64, *counted_turn as i64]),
+            Condition::All(conditions) | Condition::Any(conditions) => {
+                for condition in conditions {
+                    condition.get_progress(progress);
+                }
+            }
+            Condition::ReachCell { .. } | Condition::TurnPassed(_) => {}
+        }
+    }
+
+    // Put back progress from get_progress, missing values start over
+    pub fn set_progress(&mut self, progress: &mut impl Iterator<Item = i64>) {
+        match self {
+            Condition::Routed { seen, .. } | Condition::UnitLost { seen, .. } => *seen = progress.next().unwrap_or(0) != 0,
+            Condition::HoldCells { held, counted_turn, .. } => {
+                *held = progress.next().unwrap_or(0) as u32;
+                *counted_turn = progress.next().unwrap_or(0) as u32;
+            }
+            Condition::All(conditions) | Condition::Any(conditions) => {
+                for condition in conditions {
+                    condition.set_progress(progress);
+                }
+            }
+            Condition::ReachCell { .. } | Condition::TurnPassed(_) => {}
+        }
+    }
+
+    // Why the condition is met, None if it isn't
+    pub fn get_reason(&self, context: &ObjectiveContext) -> Option<String> {
+        match self {
+            Condition::Routed { side, seen } => {
+                let is_routed: bool = *seen && !context.units.iter().any(|unit| unit.chartype == *side);
+                is_routed.then(|| format!("Every {:?} unit was defeated", side))
+            }
+            Condition::UnitLost { tag, seen } => {
+                let is_lost: bool = *seen && !context.units.iter().any(|unit| unit.tag == *tag);
+                is_lost.then(|| format!("{} was defeated", tag))
+            }
+            Condition::ReachCell { cells, tag } => {
+                let unit: &UnitState = context.units.iter()
+                    .filter(|unit| match tag {
+                        Some(tag) => unit.tag == *tag,
+                        None => unit.chartype == CharType::Player,
+                    })
+                    .find(|unit| cells.contains(&unit.pos))?;
+
+                let name: &str = tag.as_deref().unwrap_or("A player unit");
+                Some(format!("{} reached {}, {}, {}", name, unit.pos.x, unit.pos.y, unit.pos.z))
+            }
+            Condition::HoldCells { turns, held, .. } => {
+                (*held >= *turns).then(|| format!("Held the target for {} turns", turns))
+            }
+            Condition::TurnPassed(turn) => {
+                (context.turn > *turn).then(|| format!("Turn {} is over", turn))
+            }
+            // An empty All is never met, it's more likely a mistake than meant to win straight away
+            Condition::All(conditions) => {
+                if conditions.is_empty() { return None; }
+
+                let reasons: Vec<String> = conditions.iter()
+                    .map(|condition| condition.get_reason(context))
+                    .collect::<Option<Vec<String>>>()?;
+
+                Some(reasons.join(", "))
+            }
+            Condition::Any(conditions) => {
+                conditions.iter().find_map(|condition| condition.get_reason(context))
+            }
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum BattleOutcome {
+    Won(String),
+    Lost(String),
+}
+
+// What wins and what loses a battle
+#[derive(Clone, PartialEq, Debug)]
+pub struct Objectives {
+    pub victory: Condition,
+    pub defeat: Condition,
+}
+
+impl Objectives {
+    // Check after anything that could change the outcome
+    // Losing is checked first, so losing the lord with the last enemy is still a loss
+    pub fn evaluate(&mut self, context: &ObjectiveContext) -> Option<BattleOutcome> {
+        self.victory.update(context);
+        self.defeat.update(context);
+
+        if let Some(reason) = self.defeat.get_reason(context) {
+            return Some(BattleOutcome::Lost(reason));
+        }
+
+        self.victory.get_reason(context).map(BattleOutcome::Won)
+    }
+
+    // Victory's progress then defeat's, only makes sense given the same objectives
+    pub fn get_progress(&self) -> Vec<i64> {
+        let mut progress: Vec<i64> = Vec::new();
+        self.victory.get_progress(&mut progress);
+        self.defeat.get_progress(&mut progress);
+
+        progress
+    }
+
+    pub fn set_progress(&mut self, progress: &[i64]) {
+        let mut progress = progress.iter().copied();
+        self.victory.set_progress(&mut progress);
+        self.defeat.set_progress(&mut progress);
+    }
+}
+
+impl Default for Objectives {
+    // Fight until one side is gone
+    fn default() -> Self {
+        Self {
+            victory: Condition::routed(CharType::Enemy),
+            defeat: Condition::routed(CharType::Player),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::testing;
+
+    fn unit(id: u64, pos: Vector3i, chartype: CharType, tag: &str) -> UnitState {
+        UnitState { tag: tag.to_string(), ..testing::unit(id, pos, chartype, 10) }
+    }
+
+    fn context(turn: u32, units: &[UnitState]) -> ObjectiveContext<'_> {
+        ObjectiveContext { turn, units }
+    }
+
+    // Update then check, the way Objectives::evaluate does
+    fn check(condition: &mut Condition, turn: u32, units: &[UnitState]) -> Option<String> {
+        condition.update(&context(turn, units));
+        condition.get_reason(&context(turn, units))
+    }
+
+    #[test]
+    fn all_needs_every_condition() {
+        let mut condition: Condition = Condition::All(vec![Condition::TurnPassed(1), Condition::TurnPassed(3)]);
+
+        assert_eq!(check(&mut condition, 2, &[]), None);
+        assert_eq!(check(&mut condition, 4, &[]), Some("Turn 1 is over, Turn 3 is over".to_string()));
+    }
+
+    #[test]
+    fn empty_all_is_never_met() {
+        let mut condition: Condition = Condition::All(Vec::new());
+
+        assert_eq!(check(&mut condition, 100, &[]), None);
+    }
+
+    #[test]
+    fn any_needs_one_condition() {
+        let mut condition: Condition = Condition::Any(vec![Condition::TurnPassed(5), Condition::TurnPassed(1)]);
+
+        assert_eq!(check(&mut condition, 1, &[]), None);
+        assert_eq!(check(&mut condition, 2, &[]), Some("Turn 1 is over".to_string()));
+        assert_eq!(check(&mut Condition::Any(Vec::new()), 100, &[]), None);
+    }
+
+    #[test]
+    fn sides_never_seen_are_not_routed() {
+        let mut condition: Condition = Condition::routed(CharType::Enemy);
+        let player: UnitState = unit(1, Vector3i::ZERO, CharType::Player, "");
+        let enemy: UnitState = unit(2, Vector3i::RIGHT, CharType::Enemy, "");
+
+        assert_eq!(check(&mut condition, 1, &[player.clone()]), None);
+        assert_eq!(check(&mut condition, 1, &[player.clone(), enemy]), None);
+        assert!(check(&mut condition, 1, &[player]).is_some());
+    }
+
+    #[test]
+    fn tagged_units_are_lost_once_gone() {
+        let mut condition: Condition = Condition::unit_lost("lord".to_string());
+        let lord: UnitState = unit(1, Vector3i::ZERO, CharType::Player, "lord");
+
+        assert_eq!(check(&mut condition, 1, &[]), None);
+        assert_eq!(check(&mut condition, 1, &[lord]), None);
+        assert_eq!(check(&mut condition, 1, &[]), Some("lord was defeated".to_string()));
+    }
+
+    #[test]
+    fn holding_cells_starts_over_when_one_is_lost() {
+        let target: Vector3i = Vector3i::new(2, 1, 2);
+        let mut condition: Condition = Condition::hold_cells(vec![target], 2);
+        let holder: UnitState = unit(1, target, CharType::Player, "");
+        let away: UnitState = unit(1, Vector3i::new(0, 1, 0), CharType::Player, "");
+
+        assert_eq!(check(&mut condition, 1, &[holder.clone()]), None);
+        // Same turn again doesn't count twice
+        assert_eq!(check(&mut condition, 1, &[holder.clone()]), None);
+        assert_eq!(check(&mut condition, 2, &[away]), None);
+        assert_eq!(check(&mut condition, 3, &[holder.clone()]), None);
+        assert!(check(&mut condition, 4, &[holder]).is_some());
+    }
+
+    #[test]
+    fn enemies_on_cells_dont_hold_them() {
+        let target: Vector3i = Vector3i::new(2, 1, 2);
+        let mut condition: Condition = Condition::hold_cells(vec![target], 1);
+        let enemy: UnitState = unit(1, target, CharType::Enemy, "");
+
+        assert_eq!(check(&mut condition, 1, &[enemy]), None);
+    }
+
+    #[test]
+    fn turn_passed_once_the_turn_is_over() {
+        let mut condition: Condition = Condition::TurnPassed(3);
+
+        assert_eq!(check(&mut condition, 3, &[]), None);
+        assert_eq!(check(&mut condition, 4, &[]), Some("Turn 3 is over".to_string()));
+    }
+
+    #[test]
+    fn defeat_beats_victory() {
+        let mut objectives: Objectives = Objectives {
+            victory: Condition::routed(CharType::Enemy),
+            defeat: Condition::unit_lost("lord".to_string()),
+        };
+        let lord: UnitState = unit(1, Vector3i::ZERO, CharType::Player, "lord");
+        let enemy: UnitState = unit(2, Vector3i::RIGHT, CharType::Enemy, "");
+
+        assert_eq!(objectives.evaluate(&context(1, &[lord, enemy])), None);
+        assert_eq!(objectives.evaluate(&context(1, &[])), Some(BattleOutcome::Lost("lord was defeated".to_string())));
+    }
+
+    #[test]
+    fn progress_survives_a_round_trip() {
+        let target: Vector3i = Vector3i::new(2, 1, 2);
+        let fresh: Objectives = Objectives {
+            victory: Condition::hold_cells(vec![target], 5),
+            defeat: Condition::Any(vec![Condition::routed(CharType::Player), Condition::unit_lost("lord".to_string())]),
+        };
+        let lord: UnitState = unit(1, target, CharType::Player, "lord");
+
+        let mut objectives: Objectives = fresh.clone();
+        objectives.evaluate(&context(1, &[lord.clone()]));
+        objectives.evaluate(&context(2, &[lord]));
+
+        let mut loaded: Objectives = fresh;
+        loaded.set_progress(&objectives.get_progress());
+
+        assert_eq!(loaded, objectives);
+    }
+}
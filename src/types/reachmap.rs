@@ -0,0 +1,110 @@
+use std::{cmp::Reverse, collections::{BinaryHeap, HashMap}};
+
+use godot::builtin::Vector3i;
+
+// Cheapest known way of getting to a cell
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct ReachCell {
+    pub cost: u32,
+    pub prev: Option<Vector3i>, // None only for the origin
+    pub can_stop: bool,         // False for cells that can only be passed through
+    pub halts: bool,            // Movement can't continue past this cell
+    pub fall_levels: u32,       // Levels fallen past a safe drop on the way here
+}
+
+// A single move from one cell to the next
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct ReachStep {
+    pub pos: Vector3i,
+    pub cost: u32,
+    pub can_stop: bool,
+    pub halts: bool,
+    pub fall_levels: u32,
+}
+
+// Every cell reachable from an origin within a cost budget
+#[derive(Clone, Debug)]
+pub struct ReachMap {
+    pub origin: Vector3i,
+    pub cells: HashMap<Vector3i, ReachCell>,
+}
+
+impl ReachMap {
+    pub fn new(origin: Vector3i) -> Self {
+        let mut cells: HashMap<Vector3i, ReachCell> = HashMap::new();
+        cells.insert(origin, ReachCell { cost: 0, prev: None, can_stop: true, halts: false, fall_levels: 0 });
+
+        Self {
+            origin,
+            cells,
+        }
+    }
+
+    // Dijkstra search from origin, steps gives every move that can be made from a cell
+    pub fn build<F>(origin: Vector3i, max_cost: u32, mut steps: F) -> Self
+    where
+        F: FnMut(Vector3i) -> Vec<ReachStep>,
+    {
+        let mut map: Self = Self::new(origin);
+
+        // Vector3i isn't Ord, so queue raw components to keep pop order deterministic
+        // Fall levels break ties in cost, so the safest of equally cheap routes wins
+        let mut queue: BinaryHeap<Reverse<(u32, u32, i32, i32, i32)>> = BinaryHeap::new();
+        queue.push(Reverse((0, 0, origin.x, origin.y, origin.z)));
+
+        while let Some(Reverse((cost, fall_levels, x, y, z))) = queue.pop() {
+            let pos: Vector3i = Vector3i::new(x, y, z);
+
+            let cell: ReachCell = *map.cells.get(&pos).expect("Queued cells are always in the map");
+
+            // Stale entry, a cheaper or safer route here was found after it was queued
+            if (cell.cost, cell.fall_levels) < (cost, fall_levels) { continue; }
+            if cell.halts { continue; }
+
+            for step in steps(pos) {
+                let next_cost: u32 = cost.saturating_add(step.cost);
+                // Falls add up along the path, so a cell is only as safe as the route to it
+                let next_fall_levels: u32 = fall_levels + step.fall_levels;
+
+                if next_cost > max_cost { continue; }
+                if map.cells.get(&step.pos).is_some_and(|cell| (cell.cost, cell.fall_levels) <= (next_cost, next_fall_levels)) { continue; }
+
+                map.cells.insert(step.pos, ReachCell { cost: next_cost, prev: Some(pos), can_stop: step.can_stop, halts: step.halts, fall_levels: next_fall_levels });
+                queue.push(Reverse((next_cost, next_fall_levels, step.pos.x, step.pos.y, step.pos.z)));
+            }
+        }
+
+        map
+    }
+
+    pub fn contains(&self, pos: Vector3i) -> bool {
+        self.cells.contains_key(&pos)
+    }
+
+    // Whether a unit can end its movement at pos
+    pub fn can_stop_at(&self, pos: Vector3i) -> bool {
+        self.cells.get(&pos).is_some_and(|cell| cell.can_stop)
+    }
+
+    pub fn get_cost(&self, pos: Vector3i) -> Option<u32> {
+        self.cells.get(&pos).map(|cell| cell.cost)
+    }
+
+    // Shortest path from origin to pos, including both ends
+    pub fn get_path(&self, pos: Vector3i) -> Option<Vec<Vector3i>> {
+        let mut cell: &ReachCell = self.cells.get(&pos)?;
+        let mut path: Vec<Vector3i> = vec![pos];
+
+        while let Some(prev) = cell.prev {
+            path.push(prev);
+            cell = self.cells.get(&prev).expect("Predecessors are always in the map");
+        }
+
+        path.reverse();
+        Some(path)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&Vector3i, &ReachCell)> {
+        self.cells.iter()
+    }
+}
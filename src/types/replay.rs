@@ -0,0 +1,165 @@
+use crate::types::{CharType, UnitState, PHASE_ORDER};
+
+use godot::builtin::Vector3i;
+
+const FNV_OFFSET: u64 = 0xCBF2_9CE4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01B3;
+
+// Ints each step takes up in a replay file, its kind then two cells
+pub const REPLAY_STEP_LEN: usize = 7;
+
+// Anything the player or AI did to the board, with units found by where they stood
+// Cells can be checked against the board a step is played on, so a desync shows up as soon as it happens
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ReplayStep {
+    Move { from: Vector3i, to: Vector3i },
+    Attack { from: Vector3i, target: Vector3i },
+    Heal { from: Vector3i, target: Vector3i },
+    Wait { at: Vector3i },
+    Undo,
+    Redo,
+    Rewind,
+    EndPhase, // Only when the player ended it, phases that run out end by themselves
+}
+
+impl ReplayStep {
+    pub fn to_ints(&self) -> [i32; REPLAY_STEP_LEN] {
+        let (kind, a, b): (i32, Vector3i, Vector3i) = match *self {
+            ReplayStep::Move { from, to } => (0, from, to),
+            ReplayStep::Attack { from, target } => (1, from, target),
+            ReplayStep::Heal { from, target } => (2, from, target),
+            ReplayStep::Wait { at } => (3, at, Vector3i::ZERO),
+            ReplayStep::Undo => (4, Vector3i::ZERO, Vector3i::ZERO),
+            ReplayStep::Redo => (5, Vector3i::ZERO, Vector3i::ZERO),
+            ReplayStep::Rewind => (6, Vector3i::ZERO, Vector3i::ZERO),
+            ReplayStep::EndPhase => (7, Vector3i::ZERO, Vector3i::ZERO),
+        };
+
+        [kind, a.x, a.y, a.z, b.x, b.y, b.z]
+    }
+
+    // None if the kind isn't known or there aren't enough ints
+    pub fn from_ints(ints: &[i32]) -> Option<Self> {
+        let [kind, ax, ay, az, bx, by, bz] = *ints else { return None; };
+        let (a, b): (Vector3i, Vector3i) = (Vector3i::new(ax, ay, az), Vector3i::new(bx, by, bz));
+
+        match kind {
+            0 => Some(ReplayStep::Move { from: a, to: b }),
+            1 => Some(ReplayStep::Attack { from: a, target: b }),
+            2 => Some(ReplayStep::Heal { from: a, target: b }),
+            3 => Some(ReplayStep::Wait { at: a }),
+            4 => Some(ReplayStep::Undo),
+            5 => Some(ReplayStep::Redo),
+            6 => Some(ReplayStep::Rewind),
+            7 => Some(ReplayStep::EndPhase),
+            _ => None,
+        }
+    }
+}
+
+// Every step since the battle started, and the board's checksum just before each one
+#[derive(Clone, Debug)]
+pub struct ReplayLog {
+    pub seed: u64,
+    pub steps: Vec<ReplayStep>,
+    pub checksums: Vec<u64>,
+}
+
+impl ReplayLog {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            steps: Vec::new(),
+            checksums: Vec::new(),
+        }
+    }
+
+    pub fn record(&mut self, step: ReplayStep, checksum: u64) {
+        self.steps.push(step);
+        self.checksums.push(checksum);
+    }
+}
+
+// FNV-1a over everything that decides what happens next, cheap enough to take before every step
+// Units are ordered by cell, so the order doesn't depend on how the field stores them
+pub fn get_board_checksum(units: &[UnitState], turn: u32, phase: CharType, rng_state: u64) -> u64 {
+    let mut units: Vec<&UnitState> = units.iter().collect();
+    units.sort_by_key(|unit| (unit.pos.x, unit.pos.y, unit.pos.z));
+
+    let mut hash: u64 = FNV_OFFSET;
+    hash = add_to_checksum(hash, turn as i64);
+    hash = add_to_checksum(hash, get_side_index(phase));
+    hash = add_to_checksum(hash, rng_state as i64);
+
+    for unit in units {
+        for value in [unit.pos.x, unit.pos.y, unit.pos.z, unit.stats.hp, unit.stats.max_hp] {
+            hash = add_to_checksum(hash, value as i64);
+        }
+        hash = add_to_checksum(hash, get_side_index(unit.chartype));
+    }
+
+    hash
+}
+
+fn add_to_checksum(mut hash: u64, value: i64) -> u64 {
+    for byte in value.to_le_bytes() {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(FNV_PRIME);
+    }
+
+    hash
+}
+
+fn get_side_index(chartype: CharType) -> i64 {
+    PHASE_ORDER.iter().position(|phase| *phase == chartype).expect("Every CharType is a phase") as i64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::testing::{cell, unit};
+
+    #[test]
+    fn steps_survive_a_round_trip() {
+        let steps: [ReplayStep; 8] = [
+            ReplayStep::Move { from: cell(0, 0), to: cell(2, 1) },
+            ReplayStep::Attack { from: cell(2, 1), target: cell(3, 1) },
+            ReplayStep::Heal { from: cell(1, 1), target: cell(1, 2) },
+            ReplayStep::Wait { at: cell(4, 4) },
+            ReplayStep::Undo,
+            ReplayStep::Redo,
+            ReplayStep::Rewind,
+            ReplayStep::EndPhase,
+        ];
+
+        for step in steps {
+            assert_eq!(ReplayStep::from_ints(&step.to_ints()), Some(step));
+        }
+    }
+
+    #[test]
+    fn unknown_or_short_steps_are_rejected() {
+        assert_eq!(ReplayStep::from_ints(&[8, 0, 0, 0, 0, 0, 0]), None);
+        assert_eq!(ReplayStep::from_ints(&[0, 0, 0]), None);
+    }
+
+    #[test]
+    fn checksum_doesnt_depend_on_unit_order() {
+        let units: Vec<UnitState> = vec![
+            unit(1, cell(0, 0), CharType::Player, 10),
+            unit(2, cell(3, 1), CharType::Enemy, 7),
+            unit(3, cell(1, 4), CharType::Ally, 4),
+        ];
+        let reversed: Vec<UnitState> = units.iter().rev().cloned().collect();
+
+        assert_eq!(get_board_checksum(&units, 2, CharType::Enemy, 99), get_board_checksum(&reversed, 2, CharType::Enemy, 99));
+    }
+
+    #[test]
+    fn checksum_changes_with_hp() {
+        let units: Vec<UnitState> = vec![unit(1, cell(0, 0), CharType::Player, 10)];
+        let hurt: Vec<UnitState> = vec![unit(1, cell(0, 0), CharType::Player, 9)];
+
+        assert_ne!(get_board_checksum(&units, 1, CharType::Player, 0), get_board_checksum(&hurt, 1, CharType::Player, 0));
+    }
+}
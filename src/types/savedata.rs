@@ -0,0 +1,6 @@
+use godot::{builtin::Dictionary, meta::FromGodot};
+
+// Value saved under key, or fallback if it's missing or the wrong type
+pub fn read_save_value<T: FromGodot>(data: &Dictionary, key: &str, fallback: T) -> T {
+    data.get(key).and_then(|value| value.try_to::<T>().ok()).unwrap_or(fallback)
+}
@@ -0,0 +1,47 @@
+use std::collections::HashMap;
+
+use godot::builtin::Dictionary;
+
+// Cost of stepping onto each block type, keyed by base item index
+// Blocks missing from the table cost default_cost
+#[derive(Clone, Debug)]
+pub struct TerrainCosts {
+    pub costs: HashMap<i32, Option<u32>>, // None means impassable
+    pub default_cost: u32,
+}
+
+impl TerrainCosts {
+    pub fn new() -> Self {
+        Self {
+            costs: HashMap::new(),
+            default_cost: 1,
+        }
+    }
+
+    // Negative costs in the dictionary mark a block as impassable
+    // Entries that aren't int -> int are ignored
+    pub fn from_dictionary(dict: &Dictionary) -> Self {
+        let mut table: Self = Self::new();
+
+        for (key, value) in dict.iter_shared() {
+            let (Ok(base_item), Ok(cost)) = (key.try_to::<i32>(), value.try_to::<i32>()) else { continue; };
+
+            table.costs.insert(base_item, u32::try_from(cost).ok());
+        }
+
+        table
+    }
+
+    pub fn get_cost(&self, base_item: i32) -> Option<u32> {
+        match self.costs.get(&base_item) {
+            Some(cost) => *cost,
+            None => Some(self.default_cost),
+        }
+    }
+}
+
+impl Default for TerrainCosts {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,40 @@
+use crate::types::{BattleGrid, CharType, MovementClass, UnitState, UnitStats, WeaponRange, WeaponShape};
+
+use godot::builtin::Vector3i;
+
+// Shared by the test modules, so a change to UnitState only needs fixing here
+
+// Square of floor blocks at y 0, units stand at y 1
+pub fn grid(size: i32) -> BattleGrid {
+    let mut grid: BattleGrid = BattleGrid::new();
+    grid.slope_index = -1; // Floor is block 0, which would otherwise be a slope
+
+    for x in 0..size {
+        for z in 0..size {
+            grid.set_cell(Vector3i::new(x, 0, z), 0, 0);
+        }
+    }
+
+    grid
+}
+
+// Cell a unit stands in on a flat floor at y 0
+pub fn cell(x: i32, z: i32) -> Vector3i {
+    Vector3i::new(x, 1, z)
+}
+
+// Infantry with a reach of 3, striking adjacent cells for 5 and never missing
+pub fn unit(id: u64, pos: Vector3i, chartype: CharType, hp: i32) -> UnitState {
+    UnitState {
+        id,
+        pos,
+        chartype,
+        class: MovementClass::Infantry,
+        profile: MovementClass::Infantry.get_profile(),
+        stats: UnitStats { max_hp: 10, hp, attack: 5, defence: 0, accuracy: 100, evasion: 0, critical: 0, heal_power: 0 },
+        movement_range: 3,
+        weapon: WeaponRange { shape: WeaponShape::Diamond, min_range: 1, max_range: 1, vertical_range: 1 },
+        heal_range: 0,
+        tag: String::new(),
+    }
+}
@@ -0,0 +1,193 @@
+use crate::types::CharType;
+
+use std::collections::{HashMap, HashSet};
+
+// Phases run in this order every turn
+pub const PHASE_ORDER: [CharType; 3] = [CharType::Player, CharType::Ally, CharType::Enemy];
+
+// Which side is acting and which of its units are done
+// Units are tracked by id so this never needs to touch the nodes themselves
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct TurnState {
+    pub turn: u32,
+    pub phase: CharType,
+    pub units: HashMap<u64, CharType>,
+    pub moved: HashSet<u64>,
+    pub acted: HashSet<u64>,
+}
+
+impl TurnState {
+    pub fn new() -> Self {
+        Self {
+            turn: 1,
+            phase: PHASE_ORDER[0],
+            units: HashMap::new(),
+            moved: HashSet::new(),
+            acted: HashSet::new(),
+        }
+    }
+
+    pub fn add_unit(&mut self, id: u64, chartype: CharType) {
+        self.units.insert(id, chartype);
+    }
+
+    pub fn remove_unit(&mut self, id: u64) {
+        self.units.remove(&id);
+        self.moved.remove(&id);
+        self.acted.remove(&id);
+    }
+
+    // Go to the next phase with any units in it, returns true if that started a new turn
+    // Stays put when no side has units left, as there would be no phase to stop at
+    pub fn advance_phase(&mut self) -> bool {
+        if !self.has_units() { return false; }
+
+        let mut new_turn: bool = false;
+
+        loop {
+            let index: usize = PHASE_ORDER.iter().position(|phase| *phase == self.phase).expect("Every CharType is a phase");
+            let next_index: usize = (index + 1) % PHASE_ORDER.len();
+
+            self.phase = PHASE_ORDER[next_index];
+            self.moved.clear();
+            self.acted.clear();
+
+            if next_index == 0 {
+                self.turn += 1;
+                new_turn = true;
+            }
+
+            // Cannot loop forever, as some phase has units
+            if !self.get_phase_units().is_empty() { return new_turn; }
+        }
+    }
+
+    pub fn has_units(&self) -> bool {
+        !self.units.is_empty()
+    }
+
+    pub fn mark_moved(&mut self, id: u64) {
+        self.moved.insert(id);
+    }
+
+    // Acting ends a unit's phase, whether it attacked, healed or waited
+    pub fn mark_acted(&mut self, id: u64) {
+        self.moved.insert(id);
+        self.acted.insert(id);
+    }
+
+    // Put a unit's flags back to exactly these, for loading and undoing
+    pub fn set_flags(&mut self, id: u64, moved: bool, acted: bool) {
+        if moved { self.moved.insert(id); } else { self.moved.remove(&id); }
+        if acted { self.acted.insert(id); } else { self.acted.remove(&id); }
+    }
+
+    pub fn has_moved(&self, id: u64) -> bool {
+        self.moved.contains(&id)
+    }
+
+    pub fn is_spent(&self, id: u64) -> bool {
+        self.acted.contains(&id)
+    }
+
+    // Whether the unit belongs to the current phase and can still do something
+    pub fn can_act(&self, id: u64) -> bool {
+        self.units.get(&id) == Some(&self.phase) && !self.is_spent(id)
+    }
+
+    pub fn get_phase_units(&self) -> Vec<u64> {
+        let mut units: Vec<u64> = self.units.iter()
+            .filter(|(_, chartype)| **chartype == self.phase)
+            .map(|(id, _)| *id)
+            .collect();
+
+        units.sort(); // Ids are handed out in spawn order, so this is the same every run
+        units
+    }
+
+    // True once every unit of the current phase is spent, including when there are none
+    pub fn is_phase_done(&self) -> bool {
+        self.get_phase_units().iter().all(|id| self.is_spent(*id))
+    }
+}
+
+impl Default for TurnState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn phases_without_units_are_skipped() {
+        let mut state: TurnState = TurnState::new();
+        state.add_unit(1, CharType::Player);
+        state.add_unit(2, CharType::Enemy);
+
+        assert!(!state.advance_phase());
+        assert_eq!(state.phase, CharType::Enemy);
+
+        assert!(state.advance_phase());
+        assert_eq!((state.turn, state.phase), (2, CharType::Player));
+    }
+
+    #[test]
+    fn new_turns_start_on_the_first_side_with_units() {
+        let mut state: TurnState = TurnState::new();
+        state.phase = CharType::Enemy;
+        state.add_unit(1, CharType::Ally);
+        state.add_unit(2, CharType::Enemy);
+
+        assert!(state.advance_phase());
+        assert_eq!((state.turn, state.phase), (2, CharType::Ally));
+    }
+
+    #[test]
+    fn nothing_advances_without_units() {
+        let mut state: TurnState = TurnState::new();
+
+        assert!(!state.advance_phase());
+        assert_eq!((state.turn, state.phase), (1, CharType::Player));
+    }
+
+    #[test]
+    fn advancing_clears_spent_units() {
+        let mut state: TurnState = TurnState::new();
+        state.add_unit(1, CharType::Player);
+        state.mark_acted(1);
+        assert!(state.is_phase_done());
+
+        state.advance_phase();
+
+        assert!(!state.has_moved(1));
+        assert!(state.can_act(1));
+    }
+
+    // Loading a save builds the state again from the turn, the phase and each unit's flags
+    #[test]
+    fn state_survives_a_round_trip() {
+        let mut state: TurnState = TurnState::new();
+        state.add_unit(1, CharType::Player);
+        state.add_unit(2, CharType::Player);
+        state.add_unit(3, CharType::Enemy);
+        state.advance_phase();
+        state.advance_phase();
+        state.mark_moved(1);
+        state.mark_acted(2);
+
+        let mut loaded: TurnState = TurnState::new();
+        loaded.turn = state.turn;
+        loaded.phase = state.phase;
+        for (id, chartype) in state.units.iter() {
+            loaded.add_unit(*id, *chartype);
+            if state.has_moved(*id) { loaded.mark_moved(*id); }
+            if state.is_spent(*id) { loaded.mark_acted(*id); }
+        }
+
+        assert_eq!(loaded, state);
+        assert!(loaded.can_act(1) && !loaded.can_act(2));
+    }
+}
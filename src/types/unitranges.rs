@@ -0,0 +1,46 @@
+use crate::types::{FieldCells, Occupants, ReachMap, UnitState};
+
+use std::{collections::HashSet, rc::Rc};
+use godot::builtin::Vector3i;
+
+// Everything a unit can reach from where it stands
+#[derive(Clone, Debug)]
+pub struct UnitRanges {
+    pub reachable: ReachMap,                // Where it can move to
+    pub attack_map: HashSet<Vector3i>,      // What it can attack without moving
+    pub attack_coverage: HashSet<Vector3i>, // What it can attack after moving
+    pub heal_map: ReachMap,                 // What it can heal without moving
+    pub heal_coverage: ReachMap,            // What it can heal after moving
+}
+
+impl UnitRanges {
+    pub fn build<F: FieldCells>(field: &F, unit: &UnitState, move_range: u32, occupants: &Occupants) -> Self {
+        let reachable: ReachMap = unit.get_reach_map_from(field, unit.pos, move_range, occupants);
+
+        Self {
+            attack_map: unit.get_attack_map_from(field, unit.pos),
+            attack_coverage: unit.get_attack_coverage(field, &reachable),
+            heal_map: unit.get_heal_map_from(field, unit.pos),
+            heal_coverage: unit.get_range_map_from(field, unit.pos, move_range + unit.heal_range),
+            reachable,
+        }
+    }
+}
+
+// Ranges kept until the board or the unit changes
+// The board has a version bumped on every change, the unit is compared as a whole
+#[derive(Clone, Debug)]
+pub struct RangeCache {
+    pub board_version: u64,
+    pub unit: UnitState,
+    pub move_range: u32,
+    pub ranges: Rc<UnitRanges>,
+}
+
+impl RangeCache {
+    pub fn get(&self, board_version: u64, unit: &UnitState, move_range: u32) -> Option<Rc<UnitRanges>> {
+        let is_valid: bool = self.board_version == board_version && self.move_range == move_range && self.unit == *unit;
+
+        is_valid.then(|| self.ranges.clone())
+    }
+}
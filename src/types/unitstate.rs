@@ -0,0 +1,62 @@
+use crate::types::{get_cover, get_steps, CharType, Cover, FieldCells, MovementClass, MovementProfile, Mover, Occupants, ReachMap, UnitStats, WeaponRange};
+
+use std::collections::HashSet;
+use godot::builtin::Vector3i;
+
+// Plain copy of everything the rules need about a unit, so they can run without its node
+#[derive(Clone, PartialEq, Debug)]
+pub struct UnitState {
+    pub id: u64,
+    pub pos: Vector3i,
+    pub chartype: CharType,
+    pub class: MovementClass,
+    pub profile: MovementProfile,
+    pub stats: UnitStats,
+    pub movement_range: u32,
+    pub weapon: WeaponRange,
+    pub heal_range: u32,
+    pub tag: String, // Empty if objectives don't care about the unit
+}
+
+impl UnitState {
+    pub fn get_mover<'a>(&self, occupants: &'a Occupants) -> Mover<'a> {
+        Mover {
+            class: self.class,
+            profile: self.profile,
+            chartype: self.chartype,
+            occupants,
+        }
+    }
+
+    // Reach from origin with other units blocking or slowing movement
+    pub fn get_reach_map_from<F: FieldCells>(&self, field: &F, origin: Vector3i, range: u32, occupants: &Occupants) -> ReachMap {
+        let mover: Mover = self.get_mover(occupants);
+
+        ReachMap::build(origin, range, |pos| get_steps(field, &mover, pos))
+    }
+
+    // Reach from origin ignoring every unit on the field, for the extent of attacks and heals
+    pub fn get_range_map_from<F: FieldCells>(&self, field: &F, origin: Vector3i, range: u32) -> ReachMap {
+        self.get_reach_map_from(field, origin, range, &Occupants::new())
+    }
+
+    // Cells that can be attacked when standing at origin, leaving out any in full cover
+    pub fn get_attack_map_from<F: FieldCells>(&self, field: &F, origin: Vector3i) -> HashSet<Vector3i> {
+        self.weapon.get_target_cells(field, origin).into_iter()
+            .filter(|pos| get_cover(field, origin, self.profile.height, *pos) != Cover::Full)
+            .collect()
+    }
+
+    // Cells that can be attacked from anywhere the unit can stop in reachable
+    pub fn get_attack_coverage<F: FieldCells>(&self, field: &F, reachable: &ReachMap) -> HashSet<Vector3i> {
+        reachable.iter()
+            .filter(|(_, reach_cell)| reach_cell.can_stop)
+            .flat_map(|(cell, _)| self.get_attack_map_from(field, *cell))
+            .collect()
+    }
+
+    // Cells that can be healed when standing at origin
+    pub fn get_heal_map_from<F: FieldCells>(&self, field: &F, origin: Vector3i) -> ReachMap {
+        self.get_range_map_from(field, origin, self.heal_range)
+    }
+}
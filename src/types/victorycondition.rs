@@ -0,0 +1,12 @@
+use godot::{builtin::GString, prelude::{Export, GodotConvert, Var}};
+
+// What a level is won by, the level's other settings say where, who or for how long
+#[derive(GodotConvert, Var, Export, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[godot(via = GString)]
+pub enum VictoryCondition {
+    Rout,    // Every enemy is defeated
+    Seize,   // A player unit stands on one of the target cells
+    Survive, // Hold out until the survive turn is over
+    Escort,  // The escorted unit reaches one of the target cells
+    Hold,    // Player units start enough turns in a row on every target cell
+}
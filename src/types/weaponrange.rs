@@ -0,0 +1,109 @@
+use crate::types::FieldCells;
+
+use std::collections::HashSet;
+use godot::{builtin::{GString, Vector3i}, prelude::{Export, GodotConvert, Var}};
+
+// Pattern of cells a weapon can be aimed at around its wielder
+#[derive(GodotConvert, Var, Export, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[godot(via = GString)]
+pub enum WeaponShape {
+    Diamond, // Anything within range across the field
+    Cross,   // Straight out along the four sides
+    Star,    // Straight out along the four sides and the diagonals
+    Wedges,  // Widening out from all four sides at once, range counted straight ahead
+}
+
+// Where a weapon reaches, regardless of whether anyone could walk there
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct WeaponRange {
+    pub shape: WeaponShape,
+    pub min_range: u32,
+    pub max_range: u32,
+    pub vertical_range: u32, // Levels above or below the wielder that can be hit
+}
+
+impl WeaponRange {
+    // Every offset across the field the weapon can be aimed at, ignoring height
+    pub fn get_offsets(&self) -> Vec<Vector3i> {
+        let max: i32 = self.max_range as i32;
+        let mut offsets: Vec<Vector3i> = Vec::new();
+
+        for x in -max..=max {
+            for z in -max..=max {
+                let (ax, az): (i32, i32) = (x.abs(), z.abs());
+
+                let distance: Option<i32> = match self.shape {
+                    WeaponShape::Diamond => Some(ax + az),
+                    WeaponShape::Cross => (ax == 0 || az == 0).then_some(ax + az),
+                    WeaponShape::Star => (ax == 0 || az == 0 || ax == az).then_some(ax.max(az)),
+                    // Off to the side by less than the distance ahead, diagonals belong to no side
+                    WeaponShape::Wedges => (ax != az).then_some(ax.max(az)),
+                };
+
+                let Some(distance) = distance else { continue; };
+                if distance >= self.min_range as i32 && distance <= max {
+                    offsets.push(Vector3i::new(x, 0, z));
+                }
+            }
+        }
+
+        offsets
+    }
+
+    // Cells a unit could be standing in that the weapon reaches from origin
+    // Gaps, walls and other units in between don't matter here
+    pub fn get_target_cells<F: FieldCells>(&self, field: &F, origin: Vector3i) -> HashSet<Vector3i> {
+        let vertical: i32 = self.vertical_range as i32;
+        let mut cells: HashSet<Vector3i> = HashSet::new();
+
+        for offset in self.get_offsets() {
+            for y in -vertical..=vertical {
+                let pos: Vector3i = origin + offset + Vector3i::new(0, y, 0);
+
+                if field.is_in_bounds(pos) && field.is_empty(pos) && !field.is_empty(pos + Vector3i::DOWN) {
+                    cells.insert(pos);
+                }
+            }
+        }
+
+        cells
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn offsets(shape: WeaponShape, min_range: u32, max_range: u32) -> HashSet<(i32, i32)> {
+        let weapon: WeaponRange = WeaponRange { shape, min_range, max_range, vertical_range: 1 };
+
+        weapon.get_offsets().into_iter().map(|offset| (offset.x, offset.z)).collect()
+    }
+
+    #[test]
+    fn min_range_leaves_a_gap_around_the_wielder() {
+        let cells: HashSet<(i32, i32)> = offsets(WeaponShape::Diamond, 2, 2);
+
+        assert_eq!(cells.len(), 8);
+        assert!(!cells.contains(&(1, 0)));
+        assert!(cells.contains(&(1, 1)) && cells.contains(&(0, 2)));
+    }
+
+    #[test]
+    fn stars_reach_along_sides_and_diagonals() {
+        let cells: HashSet<(i32, i32)> = offsets(WeaponShape::Star, 1, 2);
+
+        assert_eq!(cells.len(), 16);
+        assert!(cells.contains(&(2, 2)) && cells.contains(&(0, -2)));
+        assert!(!cells.contains(&(1, 2)));
+    }
+
+    #[test]
+    fn wedges_leave_out_the_diagonals() {
+        let cells: HashSet<(i32, i32)> = offsets(WeaponShape::Wedges, 1, 2);
+
+        assert_eq!(cells.len(), 16);
+        assert!(cells.contains(&(1, 2)) && cells.contains(&(2, 0)));
+        assert!(!cells.contains(&(1, 1)) && !cells.contains(&(2, 2)));
+    }
+}